@@ -0,0 +1,214 @@
+use crate::config::TEMPLATE;
+use crate::daily::{daily_setup, Date};
+use crate::title::{parse_mine_count, Difficulty, Setup, MAX_RADIUS, MAX_TIME_LIMIT};
+use crate::topology::Topology;
+
+/// What `--help` prints
+const USAGE: &str = "\
+Usage: minesweeper [options]
+
+With no board options the title screen is shown to choose one on.
+
+Board options:
+  --preset <name>    beginner, intermediate or expert
+  --width <n>        how many columns the board has
+  --height <n>       how many rows the board has
+  --mines <n>        how many mines there are, or a percentage of the cells like
+                     15% of the board given before it
+  --layers <n>       how many layers are stacked up, each one touching the next
+  --anti-mines <n>   what percentage of the mines take one off the numbers
+  --topology <name>  square, hex for six neighbours a cell, torus to wrap,
+                     knight to count the mines a knight's move away, or
+                     von-neumann for only the four cells alongside
+  --radius <n>       how many cells away neighbours can be, up to 3, on square,
+                     torus and von-neumann grids
+  --seed <n>         where the mines go, so a board can be played again
+  --daily            play today's board, which is the same for everyone
+  --no-flags         play without flags or chording, for NF times
+  --time-limit <n>   how many seconds the board has to be cleared in
+  --fog <n>          only show the board this many cells around the cursor
+  --moving-mines <n> move a mine somewhere else every this many reveals
+
+Other options:
+  --keys <layout>    the keyboard layout the keys are placed for
+  --no-color         draw without colours
+  --colourblind      use colours and styles that don't need telling apart
+  --write-default-config
+                     print a config file with every option explained
+  --help             show this and exit
+  --version          show the version and exit";
+
+/// The options given on the command line
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    pub layout: Option<String>,
+    pub no_colour: bool,
+    pub colourblind: bool,
+    /// The board to start playing on straight away, when any board options were given
+    pub setup: Option<Setup>,
+    /// The day whose daily board is being played
+    pub daily: Option<Date>,
+    /// Whether the game is played without flags, which goes with any board, the daily one too
+    pub no_flags: bool,
+    /// The seconds a time attack gives, which also goes with any board
+    pub time_limit: Option<u64>,
+    /// How far fog of war lets the board be seen, which goes with any board as well
+    pub fog: Option<usize>,
+    /// How many reveals go by between mines moving, which goes with any board too
+    pub moving_mines: Option<usize>,
+}
+
+/// What the command line asks the game to do
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Boxed, as it is much bigger than the text
+    Play(Box<Args>),
+    /// Print this, and leave without playing
+    Print(String),
+}
+
+/// Reads the command line, not counting the program's name. Board options change `base`, which
+/// is the board from the config file. Everything is checked here, before the terminal is
+/// touched, so that mistakes can be printed plainly.
+pub fn parse(args: impl IntoIterator<Item = String>, base: Setup) -> Result<Command, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Values can come after an equals sign or as the next argument
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let value = |args: &mut dyn Iterator<Item = String>| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(format!("{} needs a value", name))
+        };
+        let number = |value: String| {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("{} needs a number, not \"{}\"", name, value))
+        };
+        match name.as_str() {
+            "--help" => return Ok(Command::Print(USAGE.to_string())),
+            "--version" => {
+                let version = format!("minesweeper {}", env!("CARGO_PKG_VERSION"));
+                return Ok(Command::Print(version));
+            }
+            "--write-default-config" => return Ok(Command::Print(TEMPLATE.to_string())),
+            "--keys" => parsed.layout = Some(value(&mut args)?),
+            "--no-color" => parsed.no_colour = true,
+            // The date is only looked at once, so the board stays the same past midnight
+            "--daily" => parsed.daily = Some(Date::today()),
+            "--colourblind" => parsed.colourblind = true,
+            "--no-flags" => parsed.no_flags = true,
+            "--time-limit" => {
+                let limit = number(value(&mut args)?)? as u64;
+                if limit == 0 || limit > MAX_TIME_LIMIT {
+                    let error = "--time-limit needs between 1 and {} seconds";
+                    return Err(error.replace("{}", &MAX_TIME_LIMIT.to_string()));
+                }
+                parsed.time_limit = Some(limit);
+            }
+            "--fog" => {
+                let fog = number(value(&mut args)?)?;
+                if fog == 0 {
+                    return Err("--fog needs at least 1 cell".to_string());
+                }
+                parsed.fog = Some(fog);
+            }
+            "--moving-mines" => {
+                let every = number(value(&mut args)?)?;
+                if every == 0 {
+                    return Err("--moving-mines needs at least 1 reveal".to_string());
+                }
+                parsed.moving_mines = Some(every);
+            }
+            "--preset" => {
+                let name = value(&mut args)?;
+                let difficulty = Difficulty::from_name(&name)
+                    .ok_or(format!("There is no difficulty called \"{}\"", name))?;
+                let setup = parsed.setup.unwrap_or(base);
+                parsed.setup = Some(difficulty.setup(&setup));
+            }
+            "--mines" => {
+                let mines = value(&mut args)?;
+                let setup = parsed.setup.get_or_insert(base);
+                setup.mines = parse_mine_count(&mines, setup.cells())
+                    .map_err(|error| format!("--mines: {}", error))?;
+                setup.preset = None;
+            }
+            "--width" | "--height" | "--layers" => {
+                let count = number(value(&mut args)?)?;
+                let setup = parsed.setup.get_or_insert(base);
+                match name.as_str() {
+                    "--width" => setup.width = count,
+                    "--height" => setup.height = count,
+                    _ => setup.reshape(|setup| setup.depth = count),
+                }
+                // The board isn't one of the difficulties any more
+                setup.preset = None;
+            }
+            "--anti-mines" => {
+                let percentage = number(value(&mut args)?)?;
+                if percentage > 100 {
+                    return Err("--anti-mines needs a percentage up to 100".to_string());
+                }
+                parsed.setup.get_or_insert(base).anti_mines = percentage;
+            }
+            "--topology" => {
+                let name = value(&mut args)?;
+                let topology = Topology::from_name(&name)
+                    .ok_or(format!("There is no topology called \"{}\"", name))?;
+                parsed
+                    .setup
+                    .get_or_insert(base)
+                    .reshape(|setup| setup.topology = topology);
+            }
+            "--radius" => {
+                let radius = number(value(&mut args)?)?;
+                if radius == 0 || radius > MAX_RADIUS {
+                    let error = "--radius needs a number between 1 and {}";
+                    return Err(error.replace("{}", &MAX_RADIUS.to_string()));
+                }
+                parsed
+                    .setup
+                    .get_or_insert(base)
+                    .reshape(|setup| setup.radius = radius);
+            }
+            "--seed" => {
+                let seed = value(&mut args)?;
+                let seed = seed
+                    .parse::<u64>()
+                    .map_err(|_| format!("--seed needs a number, not \"{}\"", seed))?;
+                parsed.setup.get_or_insert(base).seed = Some(seed);
+            }
+            _ => return Err(format!("Unknown option \"{}\", see --help", arg)),
+        }
+    }
+    if let Some(date) = parsed.daily {
+        if parsed.setup.is_some() {
+            return Err("--daily picks its own board, so it can't have board options".to_string());
+        }
+        parsed.setup = Some(daily_setup(date));
+    }
+    if parsed.no_flags {
+        parsed.setup.get_or_insert(base).rules.no_flags = true;
+    }
+    if parsed.time_limit.is_some() {
+        let rules = &mut parsed.setup.get_or_insert(base).rules;
+        *rules = rules.unzen();
+        rules.time_limit = parsed.time_limit;
+    }
+    if parsed.fog.is_some() {
+        parsed.setup.get_or_insert(base).rules.fog = parsed.fog;
+    }
+    if parsed.moving_mines.is_some() {
+        parsed.setup.get_or_insert(base).rules.moving_mines = parsed.moving_mines;
+    }
+    if let Some(setup) = &parsed.setup {
+        setup.board().map_err(|error| error.to_string())?;
+    }
+    Ok(Command::Play(Box::new(parsed)))
+}
@@ -0,0 +1,2018 @@
+use crate::settings::Settings;
+use crate::theme::{number_style, Colour, GlyphSet, Glyphs};
+use crate::topology::Topology;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::*;
+use std::collections::HashSet;
+use std::fmt::Display;
+use termion::color;
+
+/// The largest width or height a board can have
+pub const MAX_SIZE: usize = 1000;
+
+/// The most layers a board can have stacked on top of each other
+pub const MAX_LAYERS: usize = 9;
+
+/// How many times to place the mines again when a number goes over the number cap
+const MAX_CAP_ATTEMPTS: usize = 20;
+
+/// The most mines a single cell can hold in the multimine variant
+pub const MAX_MINES_PER_CELL: u8 = 9;
+
+/// How many layouts are tried when looking for one with a large enough opening
+const MAX_OPENING_ATTEMPTS: usize = 100;
+
+/// Rules for placing mines around the first reveal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Opening {
+    /// Keep mines out of the 3x3 area around the first reveal so that it always cascades
+    pub clear_neighbours: bool,
+    /// The fewest cells the first reveal should open, or 0 for no minimum
+    pub min_size: usize,
+    /// Only accept layouts that can be finished from the first reveal without guessing
+    pub no_guess: bool,
+}
+
+impl Opening {
+    /// Whether mines have to wait for the first reveal before being placed
+    pub fn is_deferred(&self) -> bool {
+        self.clear_neighbours || self.min_size > 0 || self.no_guess
+    }
+}
+
+pub struct Board {
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+    /// How many mines can share a cell, which is 1 in classic minesweeper
+    pub max_mines_per_cell: u8,
+    pub cells: Vec<Cell>,
+    pub selected_row: usize,
+    pub selected_col: usize,
+    /// How many mines have been flagged, counting every flag on a cell
+    pub flags: usize,
+    /// How many of the flags are on mines
+    pub correct_flags: usize,
+    pub safe_cells_left: usize,
+    /// How many cells have no mine in them, which stays 0 until the mines are placed
+    pub safe_cells: usize,
+    /// How many mines were set off without ending the game
+    pub revealed_mines: usize,
+    pub detonated: Option<usize>,
+    /// How the board is drawn and played, which can change at any time
+    pub settings: Settings,
+    /// The highest number any safe cell should show, if there is one
+    pub number_cap: Option<i16>,
+    /// How many safe cells show a number over the number cap. It is counted whenever the numbers
+    /// change, rather than each time the status bar is drawn.
+    pub over_cap: usize,
+    /// How each cell looked before it was changed, since the last call to `take_changes`
+    pub changes: Vec<CellChange>,
+    /// The number whose chord is being previewed
+    pub preview: Option<usize>,
+    /// Cells that have been revealed but are still drawn covered, while the reveal spreads out
+    pub veiled: HashSet<usize>,
+    /// What the mines' places come from. The same seed on a board of the same size with as many
+    /// mines always gives the same layout, as long as the first reveal is in the same place.
+    pub seed: u64,
+    /// The shape of the grid, which decides which cells are neighbours
+    pub topology: Topology,
+    /// How many steps away a cell can be and still be a neighbour, on grids that have a radius
+    pub radius: usize,
+    /// How many layers of cells are stacked on top of each other, which is 1 on a flat board.
+    /// The cells of each layer come after the ones of the layer above it.
+    pub depth: usize,
+    /// The layer being shown, which the selected cell is on
+    pub layer: usize,
+    /// What percentage of the cells with mines hold anti-mines instead, which count against the
+    /// numbers around them
+    pub anti_mines: usize,
+    /// How many cells from the selected one the board is drawn, in fog of war. It comes with the
+    /// game rather than the settings, so that it can't be turned off partway through.
+    pub fog: Option<usize>,
+    rng: StdRng,
+}
+
+/// How the frame around the board is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Border {
+    None,
+    /// Box drawing characters
+    Unicode,
+    /// Plain `+`, `-` and `|` for terminals whose fonts lack box drawing characters
+    Ascii,
+}
+
+impl Border {
+    /// The top left, top right, bottom left and bottom right corners, then the horizontal and
+    /// vertical edges
+    fn glyphs(self) -> Option<[char; 6]> {
+        match self {
+            Border::None => None,
+            Border::Unicode => Some([
+                '\u{250c}', '\u{2510}', '\u{2514}', '\u{2518}', '\u{2500}', '\u{2502}',
+            ]),
+            Border::Ascii => Some(['+', '+', '+', '+', '-', '|']),
+        }
+    }
+
+    /// The arrows drawn in the top, bottom, left and right edges when there is more of the board
+    /// beyond them
+    fn arrows(self) -> [char; 4] {
+        match self {
+            Border::Ascii => ['^', 'v', '<', '>'],
+            Border::None | Border::Unicode => ['\u{25b2}', '\u{25bc}', '\u{25c0}', '\u{25b6}'],
+        }
+    }
+
+    /// The name the border goes by in the config file and on the settings screen
+    pub fn name(self) -> &'static str {
+        match self {
+            Border::None => "none",
+            Border::Unicode => "unicode",
+            Border::Ascii => "ascii",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Border> {
+        [Border::None, Border::Unicode, Border::Ascii]
+            .into_iter()
+            .find(|border| border.name() == name)
+    }
+
+    /// The style after this one, coming back round to the first
+    pub fn next(self) -> Border {
+        match self {
+            Border::None => Border::Unicode,
+            Border::Unicode => Border::Ascii,
+            Border::Ascii => Border::None,
+        }
+    }
+
+    /// How many columns and lines the frame takes up on each side of the board
+    pub fn thickness(self) -> usize {
+        match self {
+            Border::None => 0,
+            Border::Unicode | Border::Ascii => 1,
+        }
+    }
+}
+
+/// How much room is left around each cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spacing {
+    /// A space in front of each cell, and one after the last
+    Normal,
+    /// A space on both sides of each cell, which makes the columns about as far apart as the rows
+    /// so that the board looks square. The selected cell fits in brackets on its own, like `[1]`.
+    Wide,
+    /// No space between cells at all, to fit as much of a huge board on the terminal as possible
+    Compact,
+}
+
+impl Spacing {
+    /// The name the spacing goes by in the config file and on the settings screen
+    pub fn name(self) -> &'static str {
+        match self {
+            Spacing::Normal => "normal",
+            Spacing::Wide => "wide",
+            Spacing::Compact => "compact",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Spacing> {
+        [Spacing::Normal, Spacing::Wide, Spacing::Compact]
+            .into_iter()
+            .find(|spacing| spacing.name() == name)
+    }
+
+    /// The spacing after this one, coming back round to the first
+    pub fn next(self) -> Spacing {
+        match self {
+            Spacing::Normal => Spacing::Wide,
+            Spacing::Wide => Spacing::Compact,
+            Spacing::Compact => Spacing::Normal,
+        }
+    }
+
+    /// How many columns go before and after each cell, and after the last cell in a row
+    fn gaps(self) -> (usize, usize, usize) {
+        match self {
+            Spacing::Normal => (1, 0, 1),
+            Spacing::Wide => (1, 1, 0),
+            Spacing::Compact => (0, 0, 0),
+        }
+    }
+}
+
+/// The part of the board that is drawn, for boards too large to fit on the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// The top row and left column drawn
+    pub row: usize,
+    pub col: usize,
+    /// How many rows and columns are drawn
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Viewport {
+    /// A view of the whole board
+    pub fn whole(board: &Board) -> Viewport {
+        Viewport {
+            row: 0,
+            col: 0,
+            rows: board.height,
+            cols: board.width,
+        }
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        (self.row..self.row + self.rows).contains(&row)
+            && (self.col..self.col + self.cols).contains(&col)
+    }
+
+    /// Resizes the view to show as much of the board as fits in `columns` by `lines` of text, then
+    /// scrolls it as little as it takes to bring the selected cell into view
+    pub fn follow(&mut self, board: &Board, columns: usize, lines: usize) {
+        let (left, top) = board.grid_offset();
+        let thickness = board.settings.border.thickness();
+        let room = columns.saturating_sub(left + thickness + board.row_end());
+        self.cols = (room / board.cell_slot()).clamp(1, board.width);
+        self.rows = lines.saturating_sub(top + thickness).clamp(1, board.height);
+        let scroll = |start: usize, size: usize, selected: usize, total: usize| {
+            let start = if selected < start {
+                selected
+            } else if selected >= start + size {
+                selected + 1 - size
+            } else {
+                start
+            };
+            start.min(total - size)
+        };
+        self.row = scroll(self.row, self.rows, board.selected_row, board.height);
+        self.col = scroll(self.col, self.cols, board.selected_col, board.width);
+    }
+}
+
+/// A direction the selected cell can be moved in. The diagonals are for reaching every
+/// neighbour on a hex grid, and go diagonally on a square one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
+        }
+    }
+
+    /// How many rows and columns one step goes on a square grid
+    pub fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (-1, 1),
+            Direction::DownLeft => (1, -1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+}
+
+/// The state of a cell before it was revealed or marked, or had its mines moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellChange {
+    pub index: usize,
+    pub was_revealed: bool,
+    pub mark: Mark,
+    /// The mines the cell held and whether they were anti-mines
+    pub mines: (u8, bool),
+}
+
+/// The reasons a board can't be created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardError {
+    ZeroSize,
+    TooLarge,
+    /// There can be between 1 and `MAX_LAYERS` layers
+    Layers,
+    NoMines,
+    TooManyMines,
+    /// Cells must be able to hold between 1 and `MAX_MINES_PER_CELL` mines
+    MinesPerCell,
+}
+
+impl Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BoardError::ZeroSize => write!(f, "The width and height must be at least 1"),
+            BoardError::TooLarge => {
+                write!(f, "The width and height can't be larger than {}", MAX_SIZE)
+            }
+            BoardError::Layers => {
+                write!(f, "There must be between 1 and {} layers", MAX_LAYERS)
+            }
+            BoardError::NoMines => write!(f, "There must be at least 1 mine"),
+            BoardError::TooManyMines => {
+                write!(f, "There are too many mines to leave any cell without one")
+            }
+            BoardError::MinesPerCell => write!(
+                f,
+                "A cell must be able to hold between 1 and {} mines",
+                MAX_MINES_PER_CELL
+            ),
+        }
+    }
+}
+
+/// The outcome of chording on a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChordResult {
+    /// The cell isn't a revealed number with a matching amount of flags around it
+    Unsatisfied,
+    Opened,
+    /// A wrongly placed flag led to the mine at this index being revealed
+    Mine(usize),
+}
+
+/// The outcome of trying to change the mark on a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagResult {
+    Toggled,
+    /// Revealed cells can't be marked
+    Revealed,
+    /// Every flag allowed by the flag limit has already been placed
+    LimitReached,
+}
+
+/// The outcome of trying to reveal a single cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevealResult {
+    Safe,
+    Mine,
+    AlreadyRevealed,
+    Flagged,
+}
+
+impl Board {
+    /// Creates a board with every cell covered and no mines placed yet, checking that the
+    /// dimensions and mine count make for a playable game
+    pub fn new(width: usize, height: usize, mines: usize) -> Result<Board, BoardError> {
+        Board::new_multimine(width, height, mines, 1)
+    }
+
+    /// Creates a board like `new` where each cell can hold up to `max_mines_per_cell` mines
+    pub fn new_multimine(
+        width: usize,
+        height: usize,
+        mines: usize,
+        max_mines_per_cell: u8,
+    ) -> Result<Board, BoardError> {
+        Board::new_layered(width, height, 1, mines, max_mines_per_cell)
+    }
+
+    /// Creates a board like `new_multimine` with `depth` layers, each `width` by `height`, where
+    /// the cells directly above and below a cell are its neighbours too
+    pub fn new_layered(
+        width: usize,
+        height: usize,
+        depth: usize,
+        mines: usize,
+        max_mines_per_cell: u8,
+    ) -> Result<Board, BoardError> {
+        if width == 0 || height == 0 {
+            return Err(BoardError::ZeroSize);
+        }
+        if width > MAX_SIZE || height > MAX_SIZE {
+            return Err(BoardError::TooLarge);
+        }
+        if depth == 0 || depth > MAX_LAYERS {
+            return Err(BoardError::Layers);
+        }
+        if mines == 0 {
+            return Err(BoardError::NoMines);
+        }
+        if max_mines_per_cell == 0 || max_mines_per_cell > MAX_MINES_PER_CELL {
+            return Err(BoardError::MinesPerCell);
+        }
+        // At least one cell has to be left without a mine
+        if mines > (width * height * depth - 1) * max_mines_per_cell as usize {
+            return Err(BoardError::TooManyMines);
+        }
+        // Picked here so that every board has one to tell the player. Any seed can be given, but
+        // the random ones are kept short so that they are easy to pass on.
+        let seed = rand::random::<u32>() as u64;
+        Ok(Board {
+            width,
+            height,
+            mines,
+            max_mines_per_cell,
+            cells: generate_cells(width, height, depth),
+            selected_row: 0,
+            selected_col: 0,
+            flags: 0,
+            correct_flags: 0,
+            // Worked out once the mines have been placed
+            safe_cells_left: 0,
+            safe_cells: 0,
+            revealed_mines: 0,
+            detonated: None,
+            settings: Settings::default(),
+            number_cap: None,
+            over_cap: 0,
+            changes: Vec::new(),
+            preview: None,
+            veiled: HashSet::new(),
+            seed,
+            topology: Topology::default(),
+            radius: 1,
+            depth,
+            layer: 0,
+            anti_mines: 0,
+            fog: None,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    /// Places the mines from `seed` rather than the random one the board started with
+    pub fn seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Reveals the cell at the given position, opening the area around it if it has no adjacent
+    /// mines. Returns `None` if the position is outside the board.
+    pub fn reveal(&mut self, row: usize, col: usize) -> Option<RevealResult> {
+        let cell_index = cell_from_pos(row, col, self)?;
+        Some(self.reveal_index(cell_index))
+    }
+
+    fn reveal_index(&mut self, cell_index: usize) -> RevealResult {
+        if self.cells[cell_index].is_revealed {
+            return RevealResult::AlreadyRevealed;
+        }
+        if self.cells[cell_index].is_flagged() {
+            return RevealResult::Flagged;
+        }
+        self.open_cell(cell_index);
+        if self.cells[cell_index].is_mine() {
+            return RevealResult::Mine;
+        }
+        if !self.cells[cell_index].mines_nearby {
+            reveal_cells_around(self, cell_index);
+        }
+        RevealResult::Safe
+    }
+
+    /// Reveals every unflagged neighbour of a revealed number once it has as many flags around
+    /// it as it has adjacent mines
+    pub fn chord(&mut self, row: usize, col: usize) -> ChordResult {
+        match cell_from_pos(row, col, self) {
+            Some(cell_index) => self.chord_index(cell_index),
+            None => ChordResult::Unsatisfied,
+        }
+    }
+
+    fn chord_index(&mut self, cell_index: usize) -> ChordResult {
+        let cell = &self.cells[cell_index];
+        if !cell.is_revealed || cell.is_mine() || !cell.mines_nearby {
+            return ChordResult::Unsatisfied;
+        }
+        let neighbours = self.neighbours(cell_index);
+        if self.known_mines(&neighbours) != cell.adjacent_mines {
+            return ChordResult::Unsatisfied;
+        }
+        let mut result = ChordResult::Opened;
+        for index in neighbours {
+            if self.reveal_index(index) == RevealResult::Mine && result == ChordResult::Opened {
+                result = ChordResult::Mine(index);
+            }
+        }
+        result
+    }
+
+    /// Chords every revealed number around the given cell that is satisfied, then keeps chording
+    /// any numbers those chords uncover until nothing more opens. Stops as soon as a mine is hit.
+    pub fn chord_satisfied_around(&mut self, cell_index: usize) -> ChordResult {
+        let pending = self.neighbours(cell_index);
+        self.chord_satisfied(pending)
+    }
+
+    /// Chords every satisfied number on the board, along with any numbers those chords uncover,
+    /// until nothing more opens. Stops as soon as a mine is hit.
+    pub fn chord_all(&mut self) -> ChordResult {
+        let pending = (0..self.cells.len())
+            .filter(|&index| self.cells[index].is_revealed && self.cells[index].mines_nearby)
+            .collect();
+        self.chord_satisfied(pending)
+    }
+
+    /// Chords the `pending` cells that are satisfied numbers, then the numbers those chords
+    /// uncover
+    fn chord_satisfied(&mut self, mut pending: Vec<usize>) -> ChordResult {
+        let mut result = ChordResult::Unsatisfied;
+        while let Some(index) = pending.pop() {
+            let opened_from = self.changes.len();
+            match self.chord_index(index) {
+                ChordResult::Unsatisfied => {}
+                ChordResult::Opened => {
+                    result = ChordResult::Opened;
+                    // Only the numbers that were just uncovered can have become chordable
+                    pending.extend(
+                        self.changes[opened_from..]
+                            .iter()
+                            .map(|change| change.index),
+                    );
+                }
+                ChordResult::Mine(mine) => return ChordResult::Mine(mine),
+            }
+        }
+        result
+    }
+
+    /// Flags the covered neighbours of every number that has exactly as many covered neighbours
+    /// as adjacent mines, looking only at the given newly opened cells and the numbers around
+    /// them. Cells the player already marked are left alone. Returns the cells that were flagged.
+    /// Nothing is ever forced with anti-mines about, as they can make up for any mine.
+    pub fn flag_forced_around(&mut self, opened: &[usize]) -> Vec<usize> {
+        if self.anti_mines > 0 {
+            return Vec::new();
+        }
+        let mut candidates = opened.to_vec();
+        for &index in opened {
+            candidates.extend(self.neighbours(index));
+        }
+        let mut flagged = Vec::new();
+        let per_cell = self.max_mines_per_cell;
+        for index in candidates {
+            let cell = &self.cells[index];
+            if !cell.is_revealed || cell.is_mine() || cell.adjacent_mines == 0 {
+                continue;
+            }
+            let neighbours = self.neighbours(index);
+            let revealed_mines: usize = neighbours
+                .iter()
+                .filter(|&&near| self.cells[near].is_revealed)
+                .map(|&near| self.cells[near].mines as usize)
+                .sum();
+            let covered: Vec<usize> = neighbours
+                .into_iter()
+                .filter(|&near| !self.cells[near].is_revealed)
+                .collect();
+            // Only when every covered cell has to be full of mines is there nothing to guess
+            if covered.len() * per_cell as usize + revealed_mines != cell.adjacent_mines as usize {
+                continue;
+            }
+            for near in covered {
+                if self.cells[near].mark == Mark::None && self.can_flag(per_cell) {
+                    self.set_mark(near, Mark::Flag(per_cell));
+                    flagged.push(near);
+                }
+            }
+        }
+        flagged
+    }
+
+    /// The indices of every cell next to the given one, as the topology has it, along with the
+    /// cells directly above and below those and the cell itself on the layers either side. On a
+    /// board too small for its wrapped edges to stay apart, a cell can be reached more than one
+    /// way, but it is still only one neighbour, and never a neighbour of itself.
+    pub fn neighbours(&self, cell_index: usize) -> Vec<usize> {
+        let cell = &self.cells[cell_index];
+        let flat = self.topology.offsets(cell.row, self.radius);
+        let mut around = flat.clone();
+        around.push((0, 0));
+        let mut neighbours = Vec::new();
+        for layers in -1..=1 {
+            let offsets = if layers == 0 { &flat } else { &around };
+            for &(rows, cols) in offsets {
+                match relative_cell_index(layers, rows, cols, cell, self) {
+                    Some(index) if index != cell_index && !neighbours.contains(&index) => {
+                        neighbours.push(index)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Marks a single cell as revealed, keeping the count of safe cells left up to date
+    fn open_cell(&mut self, cell_index: usize) {
+        self.record_change(cell_index);
+        let cell = &mut self.cells[cell_index];
+        cell.is_revealed = true;
+        if cell.is_mine() {
+            self.revealed_mines += cell.mines as usize;
+        } else {
+            self.safe_cells_left -= 1;
+        }
+    }
+
+    /// What the mines among the given cells that are accounted for by flags, or by having been
+    /// set off already, add up to, with anti-mines taking one off each
+    fn known_mines(&self, cells: &[usize]) -> i16 {
+        cells
+            .iter()
+            .map(|&index| {
+                let cell = &self.cells[index];
+                if cell.is_revealed {
+                    cell.charge()
+                } else {
+                    cell.flag_charge()
+                }
+            })
+            .sum()
+    }
+
+    /// Places every mine once the first reveal is known, following the given opening rules. If
+    /// the board is too dense to keep the 3x3 area around the reveal clear, only the revealed cell
+    /// itself is kept clear. If no layout with a large enough opening turns up within a bounded
+    /// number of attempts, the one with the largest opening is used.
+    pub fn place_mines_around_opening(&mut self, cell_index: usize, opening: Opening) {
+        let mut forbidden = vec![cell_index];
+        if opening.clear_neighbours {
+            forbidden.extend(self.neighbours(cell_index));
+            let free_cells = self.cells.len() - forbidden.len();
+            if free_cells * (self.max_mines_per_cell as usize) < self.mines {
+                forbidden = vec![cell_index];
+            }
+        }
+        let mut best_size = 0;
+        let mut best_layout = None;
+        for _ in 0..MAX_OPENING_ATTEMPTS {
+            self.generate_mines(&forbidden);
+            let size = self.opening_size(cell_index);
+            if size >= opening.min_size {
+                best_layout = None;
+                break;
+            }
+            if best_layout.is_none() || size > best_size {
+                best_size = size;
+                best_layout = Some(self.layout());
+            }
+        }
+        if let Some(layout) = best_layout {
+            self.set_layout(layout);
+        }
+        // Flags may have been placed before there were any mines under them
+        self.correct_flags = self
+            .cells
+            .iter()
+            .map(|cell| cell.correct_flags() as usize)
+            .sum();
+    }
+
+    /// Places the mines anywhere but the `forbidden` cells and works out the numbers. With a
+    /// number cap the mines are placed again until no number goes over it, giving up after a
+    /// few attempts and keeping the layout with the fewest numbers over the cap.
+    pub fn generate_mines(&mut self, forbidden: &[usize]) {
+        let mut best_over_cap = 0;
+        let mut best_layout = None;
+        for _ in 0..MAX_CAP_ATTEMPTS {
+            for cell in self.cells.iter_mut() {
+                cell.mines = 0;
+                cell.anti = false;
+            }
+            place_mines_avoiding(
+                &mut self.cells,
+                self.mines,
+                self.max_mines_per_cell,
+                forbidden,
+                &mut self.rng,
+            );
+            self.place_anti_mines();
+            calculate_adjacent_mines(self);
+            let over_cap = self.over_cap;
+            if over_cap == 0 {
+                return;
+            }
+            if best_layout.is_none() || over_cap < best_over_cap {
+                best_over_cap = over_cap;
+                best_layout = Some(self.layout());
+            }
+        }
+        if let Some(layout) = best_layout {
+            self.set_layout(layout);
+        }
+    }
+
+    /// Turns the mines in `anti_mines` percent of the cells with mines into anti-mines, picked at
+    /// random
+    fn place_anti_mines(&mut self) {
+        let mut mined: Vec<usize> = (0..self.cells.len())
+            .filter(|&index| self.cells[index].is_mine())
+            .collect();
+        mined.shuffle(&mut self.rng);
+        let anti = (mined.len() * self.anti_mines + 50) / 100;
+        for index in mined.into_iter().take(anti) {
+            self.cells[index].anti = true;
+        }
+    }
+
+    /// Where the mines are and which of them are anti-mines, to be put back with `set_layout`
+    fn layout(&self) -> Vec<(u8, bool)> {
+        self.cells
+            .iter()
+            .map(|cell| (cell.mines, cell.anti))
+            .collect()
+    }
+
+    fn set_layout(&mut self, layout: Vec<(u8, bool)>) {
+        for (cell, (mines, anti)) in self.cells.iter_mut().zip(layout) {
+            (cell.mines, cell.anti) = (mines, anti);
+        }
+        calculate_adjacent_mines(self);
+    }
+
+    /// Counts how many safe cells show a number higher than the number cap
+    fn count_over_cap(&self) -> usize {
+        match self.number_cap {
+            Some(cap) => self
+                .cells
+                .iter()
+                .filter(|cell| !cell.is_mine() && cell.adjacent_mines > cap)
+                .count(),
+            None => 0,
+        }
+    }
+
+    /// How many cells revealing the given cell would open, without revealing anything
+    pub fn opening_size(&self, cell_index: usize) -> usize {
+        if self.cells[cell_index].is_mine() {
+            return 0;
+        }
+        let mut seen = vec![false; self.cells.len()];
+        seen[cell_index] = true;
+        let mut size = 1;
+        let mut pending = Vec::new();
+        if !self.cells[cell_index].mines_nearby {
+            pending.push(cell_index);
+        }
+        while let Some(index) = pending.pop() {
+            for near_index in self.neighbours(index) {
+                let near = &self.cells[near_index];
+                if seen[near_index] || near.is_mine() || near.is_revealed || near.is_flagged() {
+                    continue;
+                }
+                seen[near_index] = true;
+                size += 1;
+                if !near.mines_nearby {
+                    pending.push(near_index);
+                }
+            }
+        }
+        size
+    }
+
+    /// Moves the mines at `cell_index` to a random cell that doesn't have any, updating the
+    /// numbers around both cells. If every other cell already has mines they are removed instead.
+    pub fn relocate_mine(&mut self, cell_index: usize) {
+        let free_cells: Vec<usize> = (0..self.cells.len())
+            .filter(|&index| index != cell_index && !self.cells[index].is_mine())
+            .collect();
+        let (mines, anti) = (self.cells[cell_index].mines, self.cells[cell_index].anti);
+        self.cells[cell_index].mines = 0;
+        self.cells[cell_index].anti = false;
+        match free_cells.choose(&mut self.rng) {
+            Some(&new_index) => {
+                self.cells[new_index].mines = mines;
+                self.cells[new_index].anti = anti;
+                self.correct_flags += self.cells[new_index].correct_flags() as usize;
+                self.update_adjacent_mines(new_index);
+            }
+            None => {
+                self.mines -= mines as usize;
+                self.safe_cells += 1;
+                self.safe_cells_left += 1;
+            }
+        }
+        self.update_adjacent_mines(cell_index);
+    }
+
+    /// Moves the mines of a covered cell to a covered cell without any, both picked at random,
+    /// then works out the numbers around the two of them again, revealed ones included. Marked
+    /// cells are left out on both ends, so that a flag that was right stays right and no mine
+    /// ever lands under one. Both cells are recorded as changed, so that undoing the move puts
+    /// the mine back. Returns `false` if no mine could be moved.
+    pub fn shift_mine(&mut self) -> bool {
+        let unmarked = |cell: &Cell| !cell.is_revealed && cell.mark == Mark::None;
+        let mines: Vec<usize> = (0..self.cells.len())
+            .filter(|&index| unmarked(&self.cells[index]) && self.cells[index].is_mine())
+            .collect();
+        let free: Vec<usize> = (0..self.cells.len())
+            .filter(|&index| unmarked(&self.cells[index]) && !self.cells[index].is_mine())
+            .collect();
+        let (Some(&from), Some(&to)) = (mines.choose(&mut self.rng), free.choose(&mut self.rng))
+        else {
+            return false;
+        };
+        self.record_change(from);
+        self.record_change(to);
+        let (mines, anti) = (self.cells[from].mines, self.cells[from].anti);
+        (self.cells[from].mines, self.cells[from].anti) = (0, false);
+        (self.cells[to].mines, self.cells[to].anti) = (mines, anti);
+        self.update_adjacent_mines(from);
+        self.update_adjacent_mines(to);
+        true
+    }
+
+    /// Recomputes the number shown on the given cell and on each of its neighbours
+    fn update_adjacent_mines(&mut self, cell_index: usize) {
+        let mut affected = self.neighbours(cell_index);
+        affected.push(cell_index);
+        for index in affected {
+            self.cells[index].adjacent_mines = adjacent_mines(self, index);
+            self.cells[index].mines_nearby = mines_nearby(self, index);
+        }
+        self.over_cap = self.count_over_cap();
+    }
+
+    /// Uncovers every mine once the game has been lost
+    pub fn reveal_mines(&mut self) {
+        for index in 0..self.cells.len() {
+            if self.cells[index].is_mine() && !self.cells[index].is_revealed {
+                self.record_change(index);
+                self.cells[index].is_revealed = true;
+            }
+        }
+    }
+
+    /// Flags every mine that isn't flagged yet once the game has been won
+    pub fn flag_mines(&mut self) {
+        for index in 0..self.cells.len() {
+            let cell = &self.cells[index];
+            let mines = cell.mines;
+            if mines > 0 && cell.correct_flags() != mines {
+                let flag = if cell.anti {
+                    Mark::AntiFlag
+                } else {
+                    Mark::Flag
+                };
+                self.set_mark(index, flag(mines));
+            }
+        }
+    }
+
+    /// Reveals every covered cell that isn't a mine, for when the flags already give the whole
+    /// board away
+    pub fn reveal_safe_cells(&mut self) {
+        for index in 0..self.cells.len() {
+            if !self.cells[index].is_mine() && !self.cells[index].is_revealed {
+                self.open_cell(index);
+            }
+        }
+    }
+
+    /// Whether the flags are on exactly the cells that have mines, with as many flags as mines on
+    /// each of them
+    pub fn all_mines_flagged(&self) -> bool {
+        self.correct_flags + self.revealed_mines == self.mines && self.flags == self.correct_flags
+    }
+
+    /// Changes the mark on a cell, keeping the flag counts up to date
+    fn set_mark(&mut self, cell_index: usize, mark: Mark) {
+        self.record_change(cell_index);
+        let cell = &mut self.cells[cell_index];
+        self.flags -= cell.flags() as usize;
+        self.correct_flags -= cell.correct_flags() as usize;
+        cell.mark = mark;
+        self.flags += cell.flags() as usize;
+        self.correct_flags += cell.correct_flags() as usize;
+    }
+
+    fn record_change(&mut self, cell_index: usize) {
+        let cell = &self.cells[cell_index];
+        self.changes.push(CellChange {
+            index: cell_index,
+            was_revealed: cell.is_revealed,
+            mark: cell.mark,
+            mines: (cell.mines, cell.anti),
+        });
+    }
+
+    /// Hands over every change recorded since the last call
+    pub fn take_changes(&mut self) -> Vec<CellChange> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// How the cells touched by the given changes look right now
+    pub fn current_state(&self, changes: &[CellChange]) -> Vec<CellChange> {
+        changes
+            .iter()
+            .map(|change| {
+                let cell = &self.cells[change.index];
+                CellChange {
+                    index: change.index,
+                    was_revealed: cell.is_revealed,
+                    mark: cell.mark,
+                    mines: (cell.mines, cell.anti),
+                }
+            })
+            .collect()
+    }
+
+    /// Puts cells back the way they were before the given changes, newest first. Mines that
+    /// moved go back too, with the numbers around them.
+    pub fn restore(&mut self, changes: &[CellChange]) {
+        let mut moved = Vec::new();
+        for change in changes.iter().rev() {
+            let cell = &mut self.cells[change.index];
+            cell.is_revealed = change.was_revealed;
+            cell.mark = change.mark;
+            if (cell.mines, cell.anti) != change.mines {
+                (cell.mines, cell.anti) = change.mines;
+                moved.push(change.index);
+            }
+        }
+        for index in moved {
+            self.update_adjacent_mines(index);
+        }
+    }
+
+    /// Covers every cell and clears every mark again while keeping the mines where they are
+    pub fn cover_all(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.is_revealed = false;
+            cell.mark = Mark::None;
+        }
+        self.flags = 0;
+        self.correct_flags = 0;
+        self.revealed_mines = 0;
+        self.safe_cells_left = self.cells.iter().filter(|cell| !cell.is_mine()).count();
+        self.detonated = None;
+        self.changes.clear();
+        self.layer = 0;
+        self.selected_row = 0;
+        self.selected_col = 0;
+    }
+
+    /// How many safe cells have been revealed, as a percentage of all of them, which is only
+    /// 100 once every one of them has been
+    pub fn cleared_percentage(&self) -> usize {
+        let cleared = self.safe_cells - self.safe_cells_left;
+        (cleared * 100).checked_div(self.safe_cells).unwrap_or(0)
+    }
+
+    /// The number of mines that haven't been flagged or set off yet, which goes negative if more
+    /// flags than mines have been placed
+    pub fn mines_left(&self) -> isize {
+        self.mines as isize - self.flags as isize - self.revealed_mines as isize
+    }
+
+    /// Whether the flag limit leaves room for this many more flags
+    pub fn can_flag(&self, flags: u8) -> bool {
+        !self.settings.flag_limit || self.flags + self.revealed_mines + flags as usize <= self.mines
+    }
+
+    /// Moves the selected cell one step in the given direction, either stopping at the edge of the
+    /// board or wrapping around to the other side, which it always does when the board itself
+    /// wraps. Returns `false` if it was stopped.
+    pub fn move_selection(&mut self, direction: Direction) -> bool {
+        let before = (self.selected_row, self.selected_col);
+        let (rows, cols) = direction.delta();
+        let (rows, cols) = self.topology.step(self.selected_row, rows, cols);
+        let wrap = self.settings.wrap_selection || self.topology.wraps();
+        let step = |at: usize, by: isize, size: usize| {
+            let to = at as isize + by;
+            match (0..size as isize).contains(&to) {
+                true => Some(to as usize),
+                false => wrap.then_some(to.rem_euclid(size as isize) as usize),
+            }
+        };
+        if let (Some(row), Some(col)) = (
+            step(self.selected_row, rows, self.height),
+            step(self.selected_col, cols, self.width),
+        ) {
+            (self.selected_row, self.selected_col) = (row, col);
+        }
+        (self.selected_row, self.selected_col) != before
+    }
+
+    /// Moves the selected cell several steps in the given direction the same way as
+    /// `move_selection`, returning `false` if it couldn't move at all
+    pub fn move_selection_by(&mut self, direction: Direction, steps: usize) -> bool {
+        let mut moved = false;
+        for _ in 0..steps {
+            moved |= self.move_selection(direction);
+        }
+        moved
+    }
+
+    /// Finds the closest cell to `origin` that is neither revealed nor flagged, by Chebyshev
+    /// distance, searching outwards one ring at a time. Cells at the same distance are taken in
+    /// reading order. Passing the last cell that was found as `after` finds the next one out from
+    /// `origin`, so repeated searches step through every candidate instead of bouncing between
+    /// two of them. The origin itself is never a candidate.
+    pub fn next_covered_cell(
+        &self,
+        origin: (usize, usize),
+        after: Option<(usize, usize)>,
+    ) -> Option<(usize, usize)> {
+        let (row, col) = origin;
+        let distance = |(other_row, other_col): (usize, usize)| {
+            row.abs_diff(other_row).max(col.abs_diff(other_col))
+        };
+        let first_ring = after.map_or(1, distance);
+        for ring in first_ring..self.width.max(self.height) {
+            let top = row.saturating_sub(ring);
+            let bottom = (row + ring).min(self.height - 1);
+            let left = col.saturating_sub(ring);
+            let right = (col + ring).min(self.width - 1);
+            for ring_row in top..=bottom {
+                // Only the cells on the edge of the square belong to this ring
+                let ring_cols: Vec<usize> = if row.abs_diff(ring_row) == ring {
+                    (left..=right).collect()
+                } else {
+                    [
+                        col.checked_sub(ring),
+                        Some(col + ring).filter(|&c| c < self.width),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect()
+                };
+                for ring_col in ring_cols {
+                    let position = (ring_row, ring_col);
+                    if ring == first_ring && after.is_some_and(|after| position <= after) {
+                        continue;
+                    }
+                    let index = self.layer_start() + ring_row * self.width + ring_col;
+                    let cell = &self.cells[index];
+                    if !cell.is_revealed && !cell.is_flagged() {
+                        return Some(position);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// The index of the next flagged cell after the selected one in reading order, or the one
+    /// before it when going backwards, wrapping around the board and going through the layers in
+    /// turn. The selected cell is only found again if it is the only flag.
+    pub fn next_flag(&self, forwards: bool) -> Option<usize> {
+        let selected = self.selected_index();
+        let count = self.cells.len();
+        (1..=count)
+            .map(|offset| {
+                if forwards {
+                    (selected + offset) % count
+                } else {
+                    (selected + count - offset) % count
+                }
+            })
+            .find(|&index| self.cells[index].is_flagged() && !self.cells[index].is_revealed)
+    }
+
+    /// The index of the selected cell
+    pub fn selected_index(&self) -> usize {
+        self.layer_start() + self.selected_row * self.width + self.selected_col
+    }
+
+    /// Selects the cell at `index`, showing its layer
+    pub fn select(&mut self, index: usize) {
+        let cell = &self.cells[index];
+        (self.layer, self.selected_row, self.selected_col) = (cell.layer, cell.row, cell.col);
+    }
+
+    /// The index of the first cell of the layer being shown
+    fn layer_start(&self) -> usize {
+        self.layer * self.width * self.height
+    }
+
+    /// Shows the layer below the one being shown, or the one above it, keeping the same row and
+    /// column selected. Returns `false` if there is no layer that way.
+    pub fn change_layer(&mut self, down: bool) -> bool {
+        match down {
+            true if self.layer + 1 < self.depth => self.layer += 1,
+            false if self.layer > 0 => self.layer -= 1,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Moves the selected cell as far as it can go in the given direction, which for the
+    /// diagonals is the corner that way
+    pub fn move_selection_to_edge(&mut self, direction: Direction) {
+        let (rows, cols) = direction.delta();
+        match rows {
+            -1 => self.selected_row = 0,
+            1 => self.selected_row = self.height - 1,
+            _ => {}
+        }
+        match cols {
+            -1 => self.selected_col = 0,
+            1 => self.selected_col = self.width - 1,
+            _ => {}
+        }
+    }
+
+    /// How many characters wide the largest number on this board can be
+    pub fn cell_width(&self) -> usize {
+        // Every width here is counted in terminal columns rather than characters, since that is
+        // what keeps the grid lined up
+        let most = self.topology.most_neighbours(self.radius, self.depth);
+        let largest = most * self.max_mines_per_cell as usize;
+        // With anti-mines the lowest number is as long as the highest, with a minus sign
+        let sign = (self.anti_mines > 0) as usize;
+        // Cells that can hold several mines show how many after the mine or flag
+        let count = match self.max_mines_per_cell {
+            1 => 0,
+            most => most.to_string().len(),
+        };
+        (largest.to_string().len() + sign).max(self.glyphs().width + count)
+    }
+
+    /// Right aligns `text`, which takes up `columns` columns, in a cell's width, so that cells
+    /// holding more than others don't push the rest of the row along
+    fn pad(&self, text: &str, columns: usize) -> String {
+        let padding = self.cell_width().saturating_sub(columns);
+        format!("{}{}", " ".repeat(padding), text)
+    }
+
+    /// How many columns each cell takes up along with the space around it
+    pub fn cell_slot(&self) -> usize {
+        let (before, after, _) = self.settings.spacing.gaps();
+        before + self.cell_width() + after
+    }
+
+    /// How many columns of space come after the last cell of a row, before the border. Staggered
+    /// rows need one more, to make room for the rows that are pushed along.
+    pub fn row_end(&self) -> usize {
+        self.settings.spacing.gaps().2 + self.topology.is_staggered() as usize
+    }
+
+    /// How many columns the given row is pushed along by, which is 1 for the odd rows of a hex
+    /// grid so that the hexagons fit together
+    fn row_indent(&self, row: usize) -> usize {
+        (self.topology.is_staggered() && row % 2 == 1) as usize
+    }
+
+    /// The glyphs the cells are drawn with. Without colours, ASCII is the one set whose glyphs
+    /// can all be told apart by shape alone.
+    pub fn glyphs(&self) -> &'static Glyphs {
+        match self.settings.colours {
+            true => self.settings.theme.glyphs.glyphs(),
+            false => GlyphSet::Ascii.glyphs(),
+        }
+    }
+
+    /// The row and column of the cell drawn at the given column and line of the board's text when
+    /// showing `view`, counting from 0 at its top left. The space in front of each cell counts as
+    /// part of it.
+    pub fn position_at(&self, x: usize, y: usize, view: &Viewport) -> Option<(usize, usize)> {
+        let (left, top) = self.grid_offset();
+        let y = y.checked_sub(top)?;
+        let x = x.checked_sub(left + self.row_indent(view.row + y))?;
+        let col = x / self.cell_slot();
+        if col >= view.cols || y >= view.rows {
+            return None;
+        }
+        Some((view.row + y, view.col + col))
+    }
+
+    /// Cycles the mark on the cell at the given position from nothing to a flag, then to more
+    /// flags if cells can hold several mines, then to anti-mine flags the same way if there are
+    /// anti-mines, then to a question mark if those are enabled, and back. Returns `None` if the
+    /// position is outside the board.
+    pub fn toggle_flag(&mut self, row: usize, col: usize) -> Option<FlagResult> {
+        let cell_index = cell_from_pos(row, col, self)?;
+        if self.cells[cell_index].is_revealed {
+            return Some(FlagResult::Revealed);
+        }
+        let before = self.cells[cell_index].mark;
+        let most = self.max_mines_per_cell;
+        let mark = match before {
+            Mark::None => Mark::Flag(1),
+            Mark::Flag(flags) if flags < most => Mark::Flag(flags + 1),
+            Mark::Flag(_) if self.anti_mines > 0 => Mark::AntiFlag(1),
+            Mark::AntiFlag(flags) if flags < most => Mark::AntiFlag(flags + 1),
+            Mark::Flag(_) | Mark::AntiFlag(_) if self.settings.question_marks => Mark::Question,
+            Mark::Flag(_) | Mark::AntiFlag(_) | Mark::Question => Mark::None,
+        };
+        if mark.flags() > before.flags() && !self.can_flag(1) {
+            return Some(FlagResult::LimitReached);
+        }
+        self.set_mark(cell_index, mark);
+        Some(FlagResult::Toggled)
+    }
+
+    /// Puts a question mark on the cell at the given position, replacing any flags, or takes it
+    /// off again. This works whether or not flags cycle through question marks.
+    pub fn toggle_question(&mut self, row: usize, col: usize) -> Option<FlagResult> {
+        let cell_index = cell_from_pos(row, col, self)?;
+        if self.cells[cell_index].is_revealed {
+            return Some(FlagResult::Revealed);
+        }
+        let mark = match self.cells[cell_index].mark {
+            Mark::Question => Mark::None,
+            Mark::None | Mark::Flag(_) | Mark::AntiFlag(_) => Mark::Question,
+        };
+        self.set_mark(cell_index, mark);
+        Some(FlagResult::Toggled)
+    }
+
+    /// How many columns and lines the labels and border take up to the left of and above the
+    /// first cell
+    pub fn grid_offset(&self) -> (usize, usize) {
+        let thickness = self.settings.border.thickness();
+        if self.settings.labels {
+            (self.row_label_width() + 1 + thickness, 1 + thickness)
+        } else {
+            (thickness, thickness)
+        }
+    }
+
+    /// How wide the widest row label is
+    fn row_label_width(&self) -> usize {
+        self.settings.coordinates.row(self.height - 1).len()
+    }
+
+    /// The line of column labels that goes above the columns in `view`. When the labels are wider
+    /// than the cells, only every 5th or 10th column is labelled so they don't run into each other.
+    fn column_labels(&self, view: &Viewport) -> String {
+        let slot = self.cell_slot();
+        let (before, _, _) = self.settings.spacing.gaps();
+        let widest = self.settings.coordinates.col(self.width - 1).len();
+        let step = [1, 5, 10]
+            .into_iter()
+            .find(|step| step * slot > widest)
+            .unwrap_or(10);
+        let (left, _) = self.grid_offset();
+        // The last label can stick out past its cell
+        let mut line = vec![' '; left + view.cols * slot + widest];
+        // The labelled columns stay the same as the view scrolls
+        for col in (view.col..view.col + view.cols).filter(|col| col % step == 0) {
+            // Line the label up with the first character of the cell
+            let label = self.settings.coordinates.col(col);
+            let start = left + (col - view.col) * slot + before;
+            for (offset, c) in label.chars().enumerate() {
+                if let Some(place) = line.get_mut(start + offset) {
+                    *place = c;
+                }
+            }
+        }
+        line.into_iter().collect::<String>().trim_end().to_string()
+    }
+
+    /// Where the cell at `index` is drawn when showing `view`, as the column and line of the
+    /// board's text counting from 0 at its top left. Cells outside the view, or on layers that
+    /// aren't being shown, aren't drawn.
+    pub fn cell_origin(&self, index: usize, view: &Viewport) -> Option<(usize, usize)> {
+        let (left, top) = self.grid_offset();
+        let cell = &self.cells[index];
+        let (row, col) = (cell.row, cell.col);
+        if cell.layer != self.layer || !view.contains(row, col) {
+            return None;
+        }
+        let indent = self.row_indent(row);
+        let (row, col) = (row - view.row, col - view.col);
+        Some((left + indent + col * self.cell_slot(), top + row))
+    }
+
+    /// How a single cell is drawn when showing `view`, colours and all, along with the spaces
+    /// around it. The last cell in each row of the view also has the space that ends the row, so
+    /// that without colours the selected cell can be shown in brackets that take up those spaces.
+    pub fn cell_text(&self, index: usize, view: &Viewport) -> String {
+        let cell = &self.cells[index];
+        let glyphs = self.glyphs();
+        let fogged = self.is_fogged(index);
+        let ((glyph, columns), colour) = if fogged {
+            ((" ".to_string(), 1), None)
+        } else if self.veiled.contains(&index) {
+            let glyph = (glyphs.covered.to_string(), glyphs.width);
+            (glyph, self.settings.theme.covered)
+        } else if self.detonated == Some(index) {
+            let glyph = (glyphs.detonated.to_string(), glyphs.width);
+            (glyph, self.settings.theme.detonated)
+        } else if self.detonated.is_some() && cell.flags() > cell.correct_flags() {
+            // Show the flags that were placed on the wrong cells
+            let glyph = (glyphs.wrong_flag.to_string(), glyphs.width);
+            (glyph, self.settings.theme.wrong_flag)
+        } else if let (Some(colour), true) = (self.settings.theme.empty, cell.is_empty()) {
+            let glyph = (glyphs.empty.to_string(), glyphs.width);
+            (glyph, Some(colour))
+        } else if let Some(left) = self.effective_number(index) {
+            let theme = &self.settings.theme;
+            match left {
+                // More flags than mines, so at least one of them is wrong, unless anti-mines
+                // have brought the number down
+                ..=-1 if self.anti_mines == 0 => (("!".to_string(), 1), theme.wrong_flag),
+                0 => (("0".to_string(), 1), theme.number(cell.adjacent_mines)),
+                left => (
+                    (left.to_string(), left.to_string().len()),
+                    theme.number(left),
+                ),
+            }
+        } else {
+            (cell.glyph(glyphs), self.colour(cell))
+        };
+        let mut text = self.paint(colour, &self.pad(&glyph, columns));
+        let effective = self.effective_number(index);
+        // Numbers with nothing left to flag fade into the background like empty cells
+        let empty = cell.is_empty() && self.settings.theme.empty.is_some();
+        // Nothing about a cell in the fog can show through, not even its style
+        if !fogged && (empty || effective == Some(0)) {
+            text = self.style(termion::style::Faint, &text, termion::style::NoFaint);
+        }
+        let number = effective.unwrap_or(cell.adjacent_mines);
+        let styled = self.settings.number_styles && !fogged;
+        if styled && cell.is_revealed && !cell.is_mine() && number > 0 {
+            text = self.style(number_style(number), &text, termion::style::Reset);
+        }
+        let (row, col) = (cell.row, cell.col);
+        // Only the layer being shown has the selected cell on it
+        let shown = cell.layer == self.layer;
+        let selected = (self.selected_row, self.selected_col);
+        if shown && selected == (row, col) {
+            let theme = &self.settings.theme;
+            let (start, end) = theme.cursor_style.codes(theme.cursor);
+            text = self.style(start, &text, end);
+        }
+        let theme = &self.settings.theme;
+        // Tints would show where the cells in the fog are, if not what is in them
+        let tint = match self.previewed(index) {
+            _ if fogged => None,
+            Some(true) => theme.preview,
+            Some(false) => theme.preview_warning,
+            None => {
+                let selected_index = self.selected_index();
+                let nearby =
+                    self.settings.neighbourhood && self.neighbours(selected_index).contains(&index);
+                let crossed = self.settings.crosshair
+                    && shown
+                    && (row == selected.0 || col == selected.1)
+                    && index != selected_index;
+                match (nearby, crossed) {
+                    (true, _) => theme.neighbourhood,
+                    (false, true) => theme.crosshair,
+                    (false, false) => None,
+                }
+            }
+        };
+        if let Some(tint) = tint {
+            text = self.style(color::Bg(tint), &text, color::Bg(color::Reset));
+        }
+        // Without colours to show it, the selected cell is put in brackets instead. Without a
+        // space of its own after it, the closing bracket takes the space in front of the next cell.
+        let (before, after, end) = self.settings.spacing.gaps();
+        let brackets = !self.settings.colours;
+        let opens = brackets && shown && selected == (row, col);
+        let closes_before =
+            brackets && shown && after == 0 && selected.0 == row && selected.1 + 1 == col;
+        let leading = match (opens, closes_before) {
+            (true, _) => '[',
+            (_, true) => ']',
+            _ => ' ',
+        };
+        if opens && before == 0 {
+            // With no room for brackets, inverse video is all that is left to show it by, even
+            // though it is a style
+            text = format!(
+                "{}{}{}",
+                termion::style::Invert,
+                text,
+                termion::style::NoInvert
+            );
+        }
+        let closing = if opens { ']' } else { ' ' };
+        let gap = |c: char, columns: usize| c.to_string().repeat(columns);
+        let mut text = format!("{}{}{}", gap(leading, before), text, gap(closing, after));
+        if col + 1 == view.col + view.cols {
+            text += &gap(closing, end);
+        }
+        text
+    }
+
+    /// Whether the cell at `index` is too far from the selected cell to be seen in fog of war.
+    /// Cells on the other layers are always too far, so that each layer is only seen around the
+    /// cursor once it is the one showing.
+    fn is_fogged(&self, index: usize) -> bool {
+        let cell = &self.cells[index];
+        self.fog.is_some_and(|fog| {
+            let rows = cell.row.abs_diff(self.selected_row);
+            let cols = cell.col.abs_diff(self.selected_col);
+            cell.layer != self.layer || rows.max(cols) > fog
+        })
+    }
+
+    /// With effective numbers on, how many more mines the revealed number at `index` touches
+    /// than it has flags around it, counting mines that were set off as flagged. The number the
+    /// cell holds is left as it is, so this is worked out again every time it is drawn.
+    fn effective_number(&self, index: usize) -> Option<i16> {
+        let cell = &self.cells[index];
+        let number = cell.is_revealed && !cell.is_mine() && cell.mines_nearby;
+        if !self.settings.effective_numbers || !number {
+            return None;
+        }
+        let accounted = self.known_mines(&self.neighbours(index));
+        Some(cell.adjacent_mines - accounted)
+    }
+
+    /// Whether chording the previewed number would open the cell at `index`, and if so whether
+    /// the number has as many flags around it as it needs
+    fn previewed(&self, index: usize) -> Option<bool> {
+        let preview = self.preview?;
+        let cell = &self.cells[index];
+        let neighbours = self.neighbours(preview);
+        if cell.is_revealed || cell.is_flagged() || !neighbours.contains(&index) {
+            return None;
+        }
+        let flags: i16 = neighbours
+            .iter()
+            .map(|&index| self.cells[index].flag_charge())
+            .sum();
+        Some(flags == self.cells[preview].adjacent_mines)
+    }
+
+    /// How many columns of text showing `view` takes up, with the labels and border
+    pub fn text_width(&self, view: &Viewport) -> usize {
+        let (left, _) = self.grid_offset();
+        left + view.cols * self.cell_slot() + self.row_end() + self.settings.border.thickness()
+    }
+
+    /// How many lines of text showing `view` takes up, with the labels and border
+    pub fn text_height(&self, view: &Viewport) -> usize {
+        let (_, top) = self.grid_offset();
+        top + view.rows + self.settings.border.thickness()
+    }
+
+    /// Draws the part of the board inside `view`, with arrows in the border on each side that has
+    /// more of the board beyond it
+    pub fn draw(&self, view: &Viewport) -> String {
+        let mut board = String::new();
+        let border = self.settings.border.glyphs();
+        let inner_width = view.cols * self.cell_slot() + self.row_end();
+        let [up, down, left, right] = self.settings.border.arrows();
+        let edge = |horizontal: char, arrow: char, more: bool| {
+            let mut edge = vec![horizontal; inner_width];
+            if more {
+                edge[inner_width / 2] = arrow;
+            }
+            edge.into_iter().collect::<String>()
+        };
+        // Room for the row labels and the space after them
+        let margin = if self.settings.labels {
+            " ".repeat(self.row_label_width() + 1)
+        } else {
+            String::new()
+        };
+        if self.settings.labels {
+            board.push_str(&format!("{}\r\n", self.column_labels(view)));
+        }
+        if let Some([top_left, top_right, _, _, horizontal, _]) = border {
+            let edge = edge(horizontal, up, view.row > 0);
+            let edge = format!("{}{}{}", top_left, edge, top_right);
+            board.push_str(&format!("{}{}\r\n", margin, self.paint_border(&edge)));
+        }
+        let middle = view.row + view.rows / 2;
+        for row in view.row..view.row + view.rows {
+            if self.settings.labels {
+                board.push_str(&format!(
+                    "{:>width$} ",
+                    self.settings.coordinates.row(row),
+                    width = self.row_label_width()
+                ));
+            }
+            if let Some([.., vertical]) = border {
+                let more = row == middle && view.col > 0;
+                let edge = if more { left } else { vertical };
+                board.push_str(&self.paint_border(&edge.to_string()));
+            }
+            // Pushing a row along leaves that much less room at its end
+            let indent = self.row_indent(row);
+            board.push_str(&" ".repeat(indent));
+            for col in view.col..view.col + view.cols {
+                board.push_str(&self.cell_text(self.layer_start() + row * self.width + col, view));
+            }
+            let stagger = self.topology.is_staggered() as usize;
+            board.push_str(&" ".repeat(stagger - indent));
+            if let Some([.., vertical]) = border {
+                let more = row == middle && view.col + view.cols < self.width;
+                let edge = if more { right } else { vertical };
+                board.push_str(&self.paint_border(&edge.to_string()));
+            }
+            board.push_str("\r\n");
+        }
+        if let Some([_, _, bottom_left, bottom_right, horizontal, _]) = border {
+            let edge = edge(horizontal, down, view.row + view.rows < self.height);
+            let edge = format!("{}{}{}", bottom_left, edge, bottom_right);
+            board.push_str(&format!("{}{}\r\n", margin, self.paint_border(&edge)));
+        }
+        // Remove the final newline
+        board.pop();
+        board
+    }
+
+    /// The colour a cell is drawn in, if it has one
+    fn colour(&self, cell: &Cell) -> Colour {
+        let theme = &self.settings.theme;
+        if cell.is_flagged() {
+            theme.flag
+        } else if !cell.is_revealed {
+            theme.covered
+        } else if cell.is_mine() {
+            theme.mine
+        } else if cell.mines_nearby {
+            theme.number(cell.adjacent_mines)
+        } else {
+            None
+        }
+    }
+
+    /// Puts `text` between the escape codes `start` and `end`, unless colours are turned off.
+    /// Every colour and style the game draws goes through here, so that without colours nothing
+    /// but the text and the cursor movement reaches the terminal.
+    pub fn style(&self, start: impl Display, text: &str, end: impl Display) -> String {
+        match self.settings.colours {
+            true => format!("{}{}{}", start, text, end),
+            false => text.to_string(),
+        }
+    }
+
+    /// Draws `text` in a colour from the theme. Only the colour is reset afterwards, so that any
+    /// style around it carries on.
+    pub fn paint(&self, colour: Colour, text: &str) -> String {
+        match colour {
+            Some(colour) => self.style(color::Fg(colour), text, color::Fg(color::Reset)),
+            None => text.to_string(),
+        }
+    }
+
+    fn paint_border(&self, text: &str) -> String {
+        self.paint(self.settings.theme.border, text)
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.draw(&Viewport::whole(self)))
+    }
+}
+
+/// What the player has marked a covered cell with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    None,
+    /// Flags marking how many mines the player thinks the cell holds
+    Flag(u8),
+    /// Flags marking how many anti-mines the player thinks the cell holds
+    AntiFlag(u8),
+    Question,
+}
+
+impl Mark {
+    /// How many flags of either kind the mark is
+    pub fn flags(self) -> u8 {
+        match self {
+            Mark::Flag(flags) | Mark::AntiFlag(flags) => flags,
+            Mark::None | Mark::Question => 0,
+        }
+    }
+}
+
+pub struct Cell {
+    /// How many mines the cell holds
+    pub mines: u8,
+    pub is_revealed: bool,
+    pub mark: Mark,
+    /// Whether the cell's mines are anti-mines, which take one off the numbers around them
+    pub anti: bool,
+    /// The mines around the cell, less the anti-mines
+    pub adjacent_mines: i16,
+    /// Whether any mines of either kind are next to the cell. Only cells without any open their
+    /// neighbours, since with anti-mines a 0 can still have a mine next to it.
+    pub mines_nearby: bool,
+    pub layer: usize,
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Cell {
+    pub fn is_mine(&self) -> bool {
+        self.mines > 0
+    }
+
+    /// Whether the cell has been revealed and has no mines in or around it
+    pub fn is_empty(&self) -> bool {
+        self.is_revealed && !self.is_mine() && !self.mines_nearby
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        self.flags() > 0
+    }
+
+    /// How many flags of either kind are on the cell
+    pub fn flags(&self) -> u8 {
+        self.mark.flags()
+    }
+
+    /// How many of the flags on the cell match a mine under it, which anti-mine flags only do
+    /// for anti-mines
+    pub fn correct_flags(&self) -> u8 {
+        match (self.mark, self.anti) {
+            (Mark::Flag(flags), false) | (Mark::AntiFlag(flags), true) => flags.min(self.mines),
+            _ => 0,
+        }
+    }
+
+    /// What the cell's mines add to the numbers around it
+    pub fn charge(&self) -> i16 {
+        match self.anti {
+            true => -(self.mines as i16),
+            false => self.mines as i16,
+        }
+    }
+
+    /// What the cell's mines would add to the numbers around it if its flags were right
+    pub fn flag_charge(&self) -> i16 {
+        match self.mark {
+            Mark::Flag(flags) => flags as i16,
+            Mark::AntiFlag(flags) => -(flags as i16),
+            Mark::None | Mark::Question => 0,
+        }
+    }
+
+    /// How the cell is drawn with the given glyphs, and how many columns that takes up
+    pub fn glyph(&self, glyphs: &Glyphs) -> (String, usize) {
+        let glyph = |glyph: &str| (glyph.to_string(), glyphs.width);
+        // Cells holding several mines or flags show how many
+        let counted = |glyph: &str, count: u8| {
+            let count = count.to_string();
+            (format!("{}{}", glyph, count), glyphs.width + count.len())
+        };
+        if self.is_revealed {
+            let mine = if self.anti {
+                glyphs.anti_mine
+            } else {
+                glyphs.mine
+            };
+            return if self.is_mine() && self.mark == Mark::AntiFlag(self.mines) {
+                glyph(glyphs.anti_flag)
+            } else if self.is_mine() && self.is_flagged() {
+                glyph(glyphs.flag)
+            } else if self.mines > 1 {
+                counted(mine, self.mines)
+            } else if self.is_mine() {
+                glyph(mine)
+            } else if !self.mines_nearby {
+                // Leaving open areas blank makes the numbers around them easier to read
+                (" ".to_string(), 1)
+            } else {
+                let number = self.adjacent_mines.to_string();
+                let columns = number.len();
+                (number, columns)
+            };
+        }
+        match self.mark {
+            Mark::Flag(1) => glyph(glyphs.flag),
+            Mark::Flag(flags) => counted(glyphs.flag, flags),
+            Mark::AntiFlag(1) => glyph(glyphs.anti_flag),
+            Mark::AntiFlag(flags) => counted(glyphs.anti_flag, flags),
+            Mark::Question => glyph(glyphs.question),
+            Mark::None => glyph(glyphs.covered),
+        }
+    }
+}
+
+pub fn generate_cells(width: usize, height: usize, depth: usize) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    for layer in 0..depth {
+        for row in 0..height {
+            for col in 0..width {
+                cells.push(Cell {
+                    mines: 0,
+                    is_revealed: false,
+                    mark: Mark::None,
+                    anti: false,
+                    adjacent_mines: 0,
+                    mines_nearby: false,
+                    layer,
+                    row,
+                    col,
+                });
+            }
+        }
+    }
+    cells
+}
+
+/// Randomly places mines on cells that aren't in `forbidden`, putting no more than `per_cell`
+/// mines on any one cell
+pub fn place_mines_avoiding(
+    cells: &mut [Cell],
+    mines: usize,
+    per_cell: u8,
+    forbidden: &[usize],
+    rng: &mut impl Rng,
+) {
+    let mut mines_placed = 0;
+    while mines_placed < mines {
+        let index = rng.gen_range(0..cells.len());
+        if cells[index].mines < per_cell && !forbidden.contains(&index) {
+            cells[index].mines += 1;
+            mines_placed += 1;
+        }
+    }
+}
+
+/// Works out the number on every cell, along with how many safe cells there are to reveal
+pub fn calculate_adjacent_mines(board: &mut Board) {
+    for index in 0..board.cells.len() {
+        board.cells[index].adjacent_mines = adjacent_mines(board, index);
+        board.cells[index].mines_nearby = mines_nearby(board, index);
+    }
+    board.safe_cells = board.cells.iter().filter(|cell| !cell.is_mine()).count();
+    board.safe_cells_left = board
+        .cells
+        .iter()
+        .filter(|cell| !cell.is_mine() && !cell.is_revealed)
+        .count();
+    board.over_cap = board.count_over_cap();
+}
+
+/// Opens the connected region of empty cells starting at `cell_index`, along with the numbered
+/// cells bordering it. Flagged cells are never opened.
+pub fn reveal_cells_around(board: &mut Board, cell_index: usize) {
+    // An explicit stack keeps huge empty regions from overflowing the call stack
+    let mut pending = vec![cell_index];
+    while let Some(index) = pending.pop() {
+        for near_index in board.neighbours(index) {
+            let near = &board.cells[near_index];
+            if near.is_mine() || near.is_revealed || near.is_flagged() {
+                continue;
+            }
+            board.open_cell(near_index);
+            if !board.cells[near_index].mines_nearby {
+                pending.push(near_index);
+            }
+        }
+    }
+}
+
+pub fn adjacent_mines(board: &Board, cell_index: usize) -> i16 {
+    let mut count = 0;
+    for i in board.neighbours(cell_index) {
+        count += board.cells[i].charge();
+    }
+    count
+}
+
+/// Whether any mines of either kind are next to the cell at `cell_index`
+pub fn mines_nearby(board: &Board, cell_index: usize) -> bool {
+    board
+        .neighbours(cell_index)
+        .into_iter()
+        .any(|index| board.cells[index].is_mine())
+}
+
+pub fn relative_cell_index(
+    delta_layer: isize,
+    delta_row: isize,
+    delta_col: isize,
+    cell: &Cell,
+    board: &Board,
+) -> Option<usize> {
+    // Layers never wrap, as the top and bottom of the stack are nothing like each other
+    let layer = cell.layer as isize + delta_layer;
+    if layer < 0 || layer >= board.depth as isize {
+        return None;
+    }
+    let mut row = cell.row as isize + delta_row;
+    let mut col = cell.col as isize + delta_col;
+    if board.topology.wraps() {
+        row = row.rem_euclid(board.height as isize);
+        col = col.rem_euclid(board.width as isize);
+    }
+    if row < 0 || col < 0 || row >= board.height as isize || col >= board.width as isize {
+        return None;
+    }
+    let layer_start = layer as usize * board.width * board.height;
+    Some(layer_start + row as usize * board.width + col as usize)
+}
+
+/// The index of the cell at the given position on the layer being shown
+pub fn cell_from_pos(row: usize, col: usize, board: &Board) -> Option<usize> {
+    if row >= board.height || col >= board.width {
+        return None;
+    }
+    Some(board.layer_start() + row * board.width + col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board laid out like `rows`, with a mine wherever there is a `*`
+    fn board(rows: &[&str]) -> Board {
+        let mines = rows.iter().map(|row| row.matches('*').count()).sum();
+        let mut board = Board::new(rows[0].len(), rows.len(), mines).unwrap();
+        for (cell, c) in board.cells.iter_mut().zip(rows.concat().chars()) {
+            cell.mines = (c == '*') as u8;
+        }
+        calculate_adjacent_mines(&mut board);
+        board
+    }
+
+    /// Which cells are open, as `o` for open, `F` for flagged and `#` for covered
+    fn opened(board: &Board) -> Vec<String> {
+        board
+            .cells
+            .chunks(board.width)
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match (cell.is_revealed, cell.is_flagged()) {
+                        (true, _) => 'o',
+                        (false, true) => 'F',
+                        (false, false) => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// `board`, drawn without colours, labels or anything else that the text would have to
+    /// allow for
+    fn plain(rows: &[&str]) -> Board {
+        let mut board = board(rows);
+        board.settings.colours = false;
+        board.settings.labels = false;
+        board.settings.border = Border::Ascii;
+        board
+    }
+
+    /// The text of a plain board with the given rows inside its border. The last line keeps
+    /// the carriage return from its line ending.
+    fn drawn(rows: &[&str]) -> String {
+        let edge = "+".to_string() + &"-".repeat(rows[0].len() - 2) + "+";
+        let mut lines = vec![edge.clone()];
+        lines.extend(rows.iter().map(|row| row.to_string()));
+        lines.push(edge);
+        lines.join("\r\n") + "\r"
+    }
+
+    #[test]
+    fn cells_over_the_cap_are_counted_again_when_mines_move() {
+        let mut board = board(&["**.", "*..", "..."]);
+        board.number_cap = Some(2);
+        calculate_adjacent_mines(&mut board);
+        assert_eq!(board.over_cap, 1);
+        assert!(board.shift_mine());
+        assert_eq!(board.over_cap, board.count_over_cap());
+        let changes = board.take_changes();
+        board.restore(&changes);
+        assert_eq!(board.over_cap, 1);
+        board.relocate_mine(0);
+        assert_eq!(board.over_cap, board.count_over_cap());
+    }
+
+    #[test]
+    fn covered_boards_are_drawn_covered() {
+        let board = plain(&["*..", "...", "..*"]);
+        let expected = drawn(&["|[#]# # |", "| # # # |", "| # # # |"]);
+        assert_eq!(board.to_string(), expected);
+    }
+
+    #[test]
+    fn flags_and_numbers_are_drawn() {
+        let mut board = plain(&["*..", "...", "..*"]);
+        board.toggle_flag(0, 0);
+        board.reveal(0, 2);
+        let expected = drawn(&["|[F]1   |", "| # 2 1 |", "| # # # |"]);
+        assert_eq!(board.to_string(), expected);
+        board.toggle_question(2, 0);
+        board.selected_row = 1;
+        let expected = drawn(&["| F 1   |", "|[#]2 1 |", "| ? # # |"]);
+        assert_eq!(board.to_string(), expected);
+    }
+
+    #[test]
+    fn lost_boards_show_the_mines_and_the_wrong_flags() {
+        let mut board = plain(&["*..", "...", "..*"]);
+        board.toggle_flag(1, 0);
+        board.reveal(0, 2);
+        assert_eq!(board.reveal(2, 2), Some(RevealResult::Mine));
+        board.detonated = Some(8);
+        board.reveal_mines();
+        let expected = drawn(&["|[*]1   |", "| X 2 1 |", "| # # @ |"]);
+        assert_eq!(board.to_string(), expected);
+    }
+
+    #[test]
+    fn cells_with_several_mines_show_how_many_and_widen_the_rest() {
+        let mut board = plain(&["*..", "...", "..*"]);
+        board.max_mines_per_cell = 2;
+        board.cells[0].mines = 2;
+        calculate_adjacent_mines(&mut board);
+        board.reveal_mines();
+        let expected = drawn(&["|[*2] #  # |", "|  #  #  # |", "|  #  #  * |"]);
+        assert_eq!(board.to_string(), expected);
+    }
+
+    #[test]
+    fn cascade_opens_the_empty_region_and_its_border() {
+        let mut board = board(&["*....", ".....", "...*.", ".....", "....."]);
+        assert_eq!(board.reveal(4, 0), Some(RevealResult::Safe));
+        // The empty cells in the top right only touch the region through numbers
+        let expected = ["#####", "ooo##", "ooo##", "ooooo", "ooooo"];
+        assert_eq!(opened(&board), expected);
+        assert_eq!(board.safe_cells_left, 23 - 16);
+    }
+
+    #[test]
+    fn cascade_leaves_flagged_safe_cells_covered() {
+        let mut board = board(&["*....", ".....", "...*.", ".....", "....."]);
+        assert_eq!(board.toggle_flag(4, 2), Some(FlagResult::Toggled));
+        assert_eq!(board.reveal(4, 0), Some(RevealResult::Safe));
+        // The flag cuts off the empty cells past it as well
+        let expected = ["#####", "ooo##", "ooo##", "ooo##", "ooF##"];
+        assert_eq!(opened(&board), expected);
+        assert_eq!(board.cells[22].mark, Mark::Flag(1));
+        assert_eq!(board.flags, 1);
+    }
+
+    #[test]
+    fn revealing_a_number_opens_only_it() {
+        let mut board = board(&["*....", ".....", "...*.", ".....", "....."]);
+        assert_eq!(board.reveal(1, 1), Some(RevealResult::Safe));
+        let expected = ["#####", "#o###", "#####", "#####", "#####"];
+        assert_eq!(opened(&board), expected);
+    }
+
+    /// Where the mines of an expert board from `seed` go, with the first reveal in the middle
+    fn seeded_layout(seed: u64) -> Vec<(u8, bool)> {
+        let mut board = Board::new(30, 16, 99).unwrap();
+        board.seed(seed);
+        let opening = Opening {
+            clear_neighbours: true,
+            ..Opening::default()
+        };
+        board.place_mines_around_opening(8 * 30 + 15, opening);
+        board.layout()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_board() {
+        let layout = seeded_layout(1234);
+        assert_eq!(layout, seeded_layout(1234));
+        let mines: usize = layout.iter().map(|&(mines, _)| mines as usize).sum();
+        assert_eq!(mines, 99);
+    }
+
+    #[test]
+    fn different_seeds_give_different_boards() {
+        assert_ne!(seeded_layout(1234), seeded_layout(1235));
+    }
+
+    #[test]
+    fn fog_hides_the_tints_of_the_cells_in_it() {
+        let mut board = board(&["*....", ".....", ".....", ".....", "....*"]);
+        board.settings.crosshair = true;
+        board.fog = Some(1);
+        let view = Viewport::whole(&board);
+        let tinted = |board: &Board, index| board.cell_text(index, &view).contains("\x1b[48;");
+        // Along the selected row, both in sight and out of it
+        assert!(tinted(&board, 1));
+        assert!(!tinted(&board, 4));
+        assert_eq!(board.cell_text(4, &view).trim(), "");
+        board.fog = None;
+        assert!(tinted(&board, 4));
+    }
+
+    #[test]
+    fn fog_covers_the_layers_that_arent_showing() {
+        let mut board = Board::new_layered(3, 3, 2, 1, 1).unwrap();
+        board.cells[8].mines = 1;
+        calculate_adjacent_mines(&mut board);
+        board.fog = Some(1);
+        // The same place on each layer, one cell from the cursor
+        let (near, below) = (1, 9 + 1);
+        assert!(!board.is_fogged(near));
+        assert!(board.is_fogged(below));
+        board.layer = 1;
+        assert!(board.is_fogged(near));
+        assert!(!board.is_fogged(below));
+        assert!(board.is_fogged(9 + 8));
+    }
+}
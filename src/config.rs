@@ -0,0 +1,770 @@
+use crate::board::{Border, Opening, Spacing};
+use crate::coords::Notation;
+use crate::keys::*;
+use crate::settings::Settings;
+use crate::theme::*;
+use crate::title::{Difficulty, Setup, MAX_RADIUS};
+use crate::topology::Topology;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Settings read from the config file. The file is a small subset of TOML: `[section]` headers
+/// followed by `name = "value"`, `name = 10` or `name = ["value", "value"]` lines, with `#`
+/// starting a comment.
+#[derive(Default)]
+pub struct Config {
+    /// The keys each action in the `[keys]` section is bound to, by action name
+    pub keys: Vec<(String, Vec<String>)>,
+    /// The keyboard layout the default keys are moved onto, from `layout` in `[keys]`
+    pub layout: Option<String>,
+    /// How long macro playback waits between steps, from `delay_ms` in the `[macros]` section
+    pub macro_delay_ms: Option<u64>,
+    /// How long a spreading reveal waits between rings, from `reveal_delay_ms` in `[display]`
+    pub reveal_delay_ms: Option<u64>,
+    /// How many cells Shift or Ctrl with an arrow moves, from `fast_step` in `[movement]`
+    pub fast_step: Option<usize>,
+    /// Whether holding down a movement key speeds it up, from `accelerate` in `[movement]`
+    pub accelerate: Option<bool>,
+    /// How many quick presses in a row it takes to speed up, from `accelerate_after`
+    pub accelerate_after: Option<usize>,
+    /// How soon the next press has to come to count as held down, from `accelerate_window_ms`
+    pub accelerate_window_ms: Option<u64>,
+    /// The settings games start with, from the `[display]` and `[game]` sections. These are the
+    /// ones the settings screen saves.
+    pub settings: Settings,
+    /// Themes of the player's own, each from a `[theme.name]` section
+    pub themes: Vec<Theme>,
+    /// The board the title screen starts on, from the `[board]` section
+    pub setup: Setup,
+}
+
+/// What `--write-default-config` prints: every section, with each value commented out and set
+/// to what is used when it is left out
+pub const TEMPLATE: &str = r##"# Minesweeper's config file, which is read from
+# $XDG_CONFIG_HOME/minesweeper/config.toml, or ~/.config/minesweeper/config.toml.
+# Take the # off the start of a line to change it. Options given on the command line
+# win over what is here.
+
+[board]
+# The board the title screen starts on: a preset, or a size of your own
+# preset = "beginner"       # beginner, intermediate or expert
+# width = 9
+# height = 9
+# mines = 10
+# layers = 1                # boards stacked up, each touching the ones either side
+# anti_mines = 0            # percentage of the mines that take one off the numbers
+# per_cell = 1              # most mines one cell can hold, up to 9
+# number_cap = 8            # highest number a cell can show, when mines are placed
+# lives = 1
+# topology = "square"       # square, hex for six neighbours a cell, torus to wrap,
+#                           # knight to count the mines a knight's move away, or
+#                           # von-neumann for only the four cells alongside
+# radius = 1                # how many cells away neighbours can be, up to 3, on
+#                           # square, torus and von-neumann grids
+# safe_opening = false      # keep mines out of the cells around the first reveal
+# min_opening = 0           # fewest cells the first reveal opens, or 0 for any
+# no_guess = false          # only boards that can be finished without guessing
+# no_flags = false          # no flags or chording, for "NF" games
+# time_limit = 0            # seconds to clear the board in, or 0 for no limit
+# fog = 0                   # how far around the cursor can be seen, 6 say, or 0 for all
+# moving_mines = 0          # reveals between a mine moving, or 0 to keep them still
+# zen = false               # no timer, mines take back the move, and nothing is kept
+
+[display]
+# colours = true
+# theme = "classic"         # classic, dark, light, solarized, colourblind, or your own
+# glyphs = "unicode"        # unicode, emoji or ascii
+# cursor = "inverse"        # inverse, bold or background
+# border = "unicode"        # unicode, ascii or none
+# spacing = "normal"        # normal, wide or compact
+# coordinates = "from 0"    # from 0, from 1 or letters
+# labels = true
+# centre = true
+# scroll = true
+# number_styles = false
+# neighbourhood = false
+# crosshair = false
+# effective_numbers = false
+# reduce_motion = false
+# reveal_delay_ms = 15
+# bell = true
+# flash = true
+
+[game]
+# question_marks = false
+# flag_limit = false
+# auto_chord = false
+# auto_flag = false
+# wrap = false
+# pause_countdown = true    # whether pausing a time attack stops its countdown
+
+[movement]
+# fast_step = 5
+# accelerate = false
+# accelerate_after = 3
+# accelerate_window_ms = 150
+
+[macros]
+# delay_ms = 150
+
+[keys]
+# layout = "qwerty"         # qwerty, dvorak, colemak or azerty
+# Any action can be bound to one key or a list of them, like:
+# flag = ["f", "enter"]
+# reveal = "space"
+
+# A theme of your own starts from a built in one and changes some of its colours
+# [theme.mine]
+# base = "dark"
+# flag = "red"
+# number_1 = "12"
+"##;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String),
+    Write(String),
+    Syntax(usize),
+    Board(String),
+    UnknownAction(String),
+    UnknownKey(String),
+    UnknownLayout(String),
+    UnknownTheme(String),
+    Theme {
+        theme: String,
+        error: ThemeError,
+    },
+    Conflict {
+        key: String,
+        first: String,
+        second: String,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "The config file couldn't be read: {}", error),
+            ConfigError::Write(error) => {
+                write!(f, "The config file couldn't be written: {}", error)
+            }
+            ConfigError::Syntax(line) => write!(f, "Line {} of the config file isn't valid", line),
+            ConfigError::Board(error) => {
+                write!(f, "The board in the config file is wrong: {}", error)
+            }
+            ConfigError::UnknownAction(action) => write!(f, "There is no action called {}", action),
+            ConfigError::UnknownKey(key) => write!(f, "There is no key called {}", key),
+            ConfigError::UnknownLayout(layout) => {
+                write!(f, "There is no keyboard layout called {}", layout)
+            }
+            ConfigError::UnknownTheme(theme) => write!(f, "There is no theme called {}", theme),
+            ConfigError::Theme { theme, error } => match error {
+                ThemeError::UnknownField(field) => {
+                    write!(f, "The {} theme has no colour called {}", theme, field)
+                }
+                ThemeError::UnknownBase(base) => {
+                    write!(
+                        f,
+                        "The {} theme is based on {}, which isn't a theme",
+                        theme, base
+                    )
+                }
+                ThemeError::BadColour { field, value } => write!(
+                    f,
+                    "The {} colour of the {} theme isn't a colour: {}",
+                    field, theme, value
+                ),
+            },
+            ConfigError::Conflict { key, first, second } => {
+                write!(
+                    f,
+                    "The {} key is bound to both {} and {}",
+                    key, first, second
+                )
+            }
+        }
+    }
+}
+
+/// Where the config file lives, following the XDG base directory spec
+pub fn config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("minesweeper").join("config.toml"))
+}
+
+impl Config {
+    /// Reads the config file, treating a missing file like an empty one
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = match config_path() {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        };
+        match std::fs::read_to_string(path) {
+            Ok(text) => Config::parse(&text),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(error) => Err(ConfigError::Io(error.to_string())),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        // Themes are put together once the whole file has been read, so that a theme can be
+        // chosen before it is defined
+        let mut theme_name = None;
+        let mut theme_colours: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = name.trim().to_string();
+                if let Some(theme) = section.strip_prefix("theme.") {
+                    theme_colours.push((theme.to_string(), Vec::new()));
+                }
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or(ConfigError::Syntax(number + 1))?;
+            let values = parse_values(value.trim()).ok_or(ConfigError::Syntax(number + 1))?;
+            let name = name.trim();
+            // Sections and names other than these are left for newer versions of the game
+            match (section.as_str(), name) {
+                ("keys", "layout") => config.layout = Some(single(&values, number)?),
+                ("board", "preset") => {
+                    let name: String = single(&values, number)?;
+                    let difficulty =
+                        Difficulty::from_name(&name).ok_or(ConfigError::Syntax(number + 1))?;
+                    config.setup = difficulty.setup(&config.setup);
+                }
+                ("board", name @ ("width" | "height" | "layers" | "mines" | "lives")) => {
+                    let count = single(&values, number)?;
+                    let setup = &mut config.setup;
+                    match name {
+                        "width" => setup.width = count,
+                        "height" => setup.height = count,
+                        "layers" => setup.reshape(|setup| setup.depth = count),
+                        "mines" => setup.mines = count,
+                        _ => setup.lives = count,
+                    }
+                    // The board isn't one of the difficulties any more
+                    if name != "lives" {
+                        setup.preset = None;
+                    }
+                }
+                ("board", "per_cell") => {
+                    config.setup.per_cell = single(&values, number)?;
+                    config.setup.preset = None;
+                }
+                ("board", "number_cap") => {
+                    config.setup.number_cap = single(&values, number)?;
+                    config.setup.preset = None;
+                }
+                ("board", "moving_mines") => {
+                    let every: usize = single(&values, number)?;
+                    config.setup.rules.moving_mines = (every > 0).then_some(every);
+                }
+                ("board", "fog") => {
+                    let fog: usize = single(&values, number)?;
+                    config.setup.rules.fog = (fog > 0).then_some(fog);
+                }
+                ("board", "time_limit") => {
+                    let limit: u64 = single(&values, number)?;
+                    config.setup.rules.time_limit = (limit > 0).then_some(limit);
+                }
+                ("board", "topology") => {
+                    let name: String = single(&values, number)?;
+                    let topology =
+                        Topology::from_name(&name).ok_or(ConfigError::Syntax(number + 1))?;
+                    config.setup.reshape(|setup| setup.topology = topology);
+                }
+                ("board", "anti_mines") => {
+                    let percentage = single(&values, number)?;
+                    if percentage > 100 {
+                        return Err(ConfigError::Syntax(number + 1));
+                    }
+                    config.setup.anti_mines = percentage;
+                }
+                ("board", "radius") => {
+                    let radius = single(&values, number)?;
+                    if radius == 0 || radius > MAX_RADIUS {
+                        return Err(ConfigError::Syntax(number + 1));
+                    }
+                    config.setup.reshape(|setup| setup.radius = radius);
+                }
+                ("board", "no_flags") => config.setup.rules.no_flags = single(&values, number)?,
+                ("board", "zen") => {
+                    let rules = config.setup.rules;
+                    config.setup.rules = match single(&values, number)? {
+                        true => rules.zen(),
+                        false => rules.unzen(),
+                    };
+                }
+                ("board", "safe_opening") => {
+                    config.setup.opening.clear_neighbours = single(&values, number)?
+                }
+                ("board", "min_opening") => {
+                    config.setup.opening.min_size = single(&values, number)?
+                }
+                ("board", "no_guess") => config.setup.opening.no_guess = single(&values, number)?,
+                ("keys", _) => config.keys.push((name.to_string(), values)),
+                ("macros", "delay_ms") => config.macro_delay_ms = Some(single(&values, number)?),
+                ("movement", "fast_step") => config.fast_step = Some(single(&values, number)?),
+                ("movement", "accelerate") => config.accelerate = Some(single(&values, number)?),
+                ("movement", "accelerate_after") => {
+                    config.accelerate_after = Some(single(&values, number)?)
+                }
+                ("movement", "accelerate_window_ms") => {
+                    config.accelerate_window_ms = Some(single(&values, number)?)
+                }
+                ("display", "reveal_delay_ms") => {
+                    config.reveal_delay_ms = Some(single(&values, number)?)
+                }
+                ("display", "spacing") => {
+                    let name: String = single(&values, number)?;
+                    config.settings.spacing =
+                        Spacing::from_name(&name).ok_or(ConfigError::Syntax(number + 1))?;
+                }
+                ("display", "coordinates") => {
+                    let name: String = single(&values, number)?;
+                    config.settings.coordinates =
+                        Notation::from_name(&name).ok_or(ConfigError::Syntax(number + 1))?;
+                }
+                // From before there was any choice besides numbering from 0 or from 1
+                ("display", "one_based") => {
+                    let one_based: bool = single(&values, number)?;
+                    if one_based {
+                        config.settings.coordinates = Notation::OneBased;
+                    }
+                }
+                ("display", "border") => {
+                    let name: String = single(&values, number)?;
+                    config.settings.border =
+                        Border::from_name(&name).ok_or(ConfigError::Syntax(number + 1))?;
+                }
+                ("display", "theme") => theme_name = Some(single::<String>(&values, number)?),
+                (section, field) if section.starts_with("theme.") => {
+                    if let Some((_, colours)) = theme_colours.last_mut() {
+                        colours.push((field.to_string(), single(&values, number)?));
+                    }
+                }
+                ("display", "cursor") => {
+                    let name: String = single(&values, number)?;
+                    config.settings.theme.cursor_style =
+                        CursorStyle::from_name(&name).ok_or(ConfigError::Syntax(number + 1))?;
+                }
+                ("display", "glyphs") => {
+                    let name: String = single(&values, number)?;
+                    config.settings.theme.glyphs =
+                        GlyphSet::from_name(&name).ok_or(ConfigError::Syntax(number + 1))?;
+                }
+                (section @ ("display" | "game"), name) => {
+                    let settings = &mut config.settings;
+                    let switch = match (section, name) {
+                        ("display", "colours") => &mut settings.colours,
+                        ("display", "labels") => &mut settings.labels,
+                        ("display", "centre") => &mut settings.centre,
+                        ("display", "scroll") => &mut settings.scroll,
+                        ("display", "number_styles") => &mut settings.number_styles,
+                        ("display", "neighbourhood") => &mut settings.neighbourhood,
+                        ("display", "crosshair") => &mut settings.crosshair,
+                        ("display", "reduce_motion") => &mut settings.reduce_motion,
+                        ("display", "effective_numbers") => &mut settings.effective_numbers,
+                        ("display", "bell") => &mut settings.bell,
+                        ("display", "flash") => &mut settings.flash,
+                        ("game", "question_marks") => &mut settings.question_marks,
+                        ("game", "flag_limit") => &mut settings.flag_limit,
+                        ("game", "auto_chord") => &mut settings.auto_chord,
+                        ("game", "auto_flag") => &mut settings.auto_flag,
+                        ("game", "wrap") => &mut settings.wrap_selection,
+                        ("game", "pause_countdown") => &mut settings.pause_countdown,
+                        _ => continue,
+                    };
+                    *switch = single(&values, number)?;
+                }
+                _ => {}
+            }
+        }
+        for (name, colours) in theme_colours {
+            let error = |error| ConfigError::Theme {
+                theme: name.clone(),
+                error,
+            };
+            // The base has to be known before any of the colours can go on top of it
+            let base = colours
+                .iter()
+                .find(|(field, _)| field == "base")
+                .map_or("classic", |(_, base)| base.as_str());
+            let mut theme = Theme::based_on(&name, base).map_err(error)?;
+            for (field, value) in colours.iter().filter(|(field, _)| field != "base") {
+                theme.set(field, value).map_err(error)?;
+            }
+            config.themes.push(theme);
+        }
+        if let Some(name) = theme_name {
+            config.use_theme(&name)?;
+        }
+        config
+            .setup
+            .board()
+            .map_err(|error| ConfigError::Board(error.to_string()))?;
+        // Anything the board doesn't mind, like a cap above the highest number, is brought
+        // within what the custom form would show
+        config.setup.clamp();
+        Ok(config)
+    }
+
+    /// Makes the theme called `name` the one games start with, keeping the glyphs and cursor style
+    pub fn use_theme(&mut self, name: &str) -> Result<(), ConfigError> {
+        let theme = self
+            .all_themes()
+            .into_iter()
+            .find(|theme| theme.name == name)
+            .ok_or_else(|| ConfigError::UnknownTheme(name.to_string()))?;
+        self.settings.theme = Theme {
+            glyphs: self.settings.theme.glyphs,
+            cursor_style: self.settings.theme.cursor_style,
+            ..theme
+        };
+        Ok(())
+    }
+
+    /// The built in themes and the player's own, which replace built in ones of the same name
+    pub fn all_themes(&self) -> Vec<Theme> {
+        let mut themes = builtin_themes();
+        for theme in &self.themes {
+            match themes.iter_mut().find(|builtin| builtin.name == theme.name) {
+                Some(builtin) => *builtin = theme.clone(),
+                None => themes.push(theme.clone()),
+            }
+        }
+        themes
+    }
+
+    /// Writes `settings` into the config file, along with how the first reveal opens up, keeping
+    /// everything else in it as it was. Returns where the file is.
+    pub fn save_settings(settings: &Settings, opening: &Opening) -> Result<PathBuf, ConfigError> {
+        let path = config_path()
+            .ok_or_else(|| ConfigError::Write("there is no home directory".to_string()))?;
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(ConfigError::Io(error.to_string())),
+        };
+        let text = set_values(
+            &text,
+            "display",
+            &[
+                ("border", format!("\"{}\"", settings.border.name())),
+                ("spacing", format!("\"{}\"", settings.spacing.name())),
+                ("glyphs", format!("\"{}\"", settings.theme.glyphs.name())),
+                (
+                    "cursor",
+                    format!("\"{}\"", settings.theme.cursor_style.name()),
+                ),
+                ("theme", format!("\"{}\"", settings.theme.name)),
+                ("colours", settings.colours.to_string()),
+                ("labels", settings.labels.to_string()),
+                ("centre", settings.centre.to_string()),
+                ("scroll", settings.scroll.to_string()),
+                (
+                    "coordinates",
+                    format!("\"{}\"", settings.coordinates.name()),
+                ),
+                ("number_styles", settings.number_styles.to_string()),
+                ("neighbourhood", settings.neighbourhood.to_string()),
+                ("crosshair", settings.crosshair.to_string()),
+                ("reduce_motion", settings.reduce_motion.to_string()),
+                ("effective_numbers", settings.effective_numbers.to_string()),
+                ("bell", settings.bell.to_string()),
+                ("flash", settings.flash.to_string()),
+            ],
+        );
+        let text = set_values(
+            &text,
+            "game",
+            &[
+                ("question_marks", settings.question_marks.to_string()),
+                ("flag_limit", settings.flag_limit.to_string()),
+                ("auto_chord", settings.auto_chord.to_string()),
+                ("auto_flag", settings.auto_flag.to_string()),
+                ("wrap", settings.wrap_selection.to_string()),
+                ("pause_countdown", settings.pause_countdown.to_string()),
+            ],
+        );
+        let text = set_values(
+            &text,
+            "board",
+            &[
+                ("safe_opening", opening.clear_neighbours.to_string()),
+                ("min_opening", opening.min_size.to_string()),
+            ],
+        );
+        let write = |path: &PathBuf| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, text)
+        };
+        write(&path).map_err(|error| ConfigError::Write(error.to_string()))?;
+        Ok(path)
+    }
+
+    /// The default keymap, moved onto the chosen keyboard layout, with the bindings from the
+    /// `[keys]` section in place of the defaults
+    pub fn keymap(&self) -> Result<Keymap, ConfigError> {
+        let mut keymap = match &self.layout {
+            Some(layout) => Keymap::for_layout(layout)
+                .ok_or_else(|| ConfigError::UnknownLayout(layout.clone()))?,
+            None => Keymap::default(),
+        };
+        for (name, key_names) in &self.keys {
+            let action =
+                Action::from_name(name).ok_or_else(|| ConfigError::UnknownAction(name.clone()))?;
+            let keys = key_names
+                .iter()
+                .map(|key| parse_key(key).ok_or_else(|| ConfigError::UnknownKey(key.clone())))
+                .collect::<Result<Vec<_>, _>>()?;
+            keymap.rebind(action, &keys);
+        }
+        if let Some(&(key, first, second)) = keymap.conflicts().first() {
+            return Err(ConfigError::Conflict {
+                key: key_name(key),
+                first: first.name(),
+                second: second.name(),
+            });
+        }
+        Ok(keymap)
+    }
+}
+
+/// Sets each name in `section` of the config file's text to its value, replacing the line it is
+/// already on or adding it to the end of the section. The section is added if there isn't one.
+fn set_values(text: &str, section: &str, values: &[(&str, String)]) -> String {
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut missing: Vec<&(&str, String)> = values.iter().collect();
+    let mut current = String::new();
+    // Where the last line of the section is, so that missing names can go after it
+    let mut end = None;
+    for (index, line) in lines.iter_mut().enumerate() {
+        let bare = strip_comment(line).trim();
+        if let Some(name) = bare
+            .strip_prefix('[')
+            .and_then(|bare| bare.strip_suffix(']'))
+        {
+            current = name.trim().to_string();
+            if current == section {
+                end = Some(index);
+            }
+            continue;
+        }
+        if current != section {
+            continue;
+        }
+        if !bare.is_empty() {
+            end = Some(index);
+        }
+        let name = bare.split_once('=').map(|(name, _)| name.trim());
+        if let Some(position) = missing.iter().position(|(wanted, _)| Some(*wanted) == name) {
+            let (name, value) = missing.remove(position);
+            // Keep any comment at the end of the line
+            let comment = line[strip_comment(line).len()..].to_string();
+            *line = format!("{} = {}", name, value);
+            if !comment.is_empty() {
+                *line += &format!(" {}", comment);
+            }
+        }
+    }
+    let added = missing
+        .iter()
+        .map(|(name, value)| format!("{} = {}", name, value));
+    match end {
+        Some(end) => {
+            lines.splice(end + 1..end + 1, added.collect::<Vec<_>>());
+        }
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", section));
+            lines.extend(added);
+        }
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Reads the one value on line `number`, counting from 0, as a number or `true` or `false`
+fn single<T: FromStr>(values: &[String], number: usize) -> Result<T, ConfigError> {
+    match values {
+        [value] => value.parse().map_err(|_| ConfigError::Syntax(number + 1)),
+        _ => Err(ConfigError::Syntax(number + 1)),
+    }
+}
+
+/// Removes a `#` comment that isn't inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Reads a quoted string or a list of quoted strings. Numbers and other bare words are read as
+/// they are.
+fn parse_values(value: &str) -> Option<Vec<String>> {
+    let mut rest = match value.strip_prefix('[') {
+        Some(list) => list.strip_suffix(']')?.trim(),
+        None if !value.starts_with('"') => {
+            if value.is_empty() || value.contains(char::is_whitespace) {
+                return None;
+            }
+            return Some(vec![value.to_string()]);
+        }
+        None => {
+            let (string, rest) = parse_string(value)?;
+            return if rest.trim().is_empty() {
+                Some(vec![string])
+            } else {
+                None
+            };
+        }
+    };
+    let mut values = Vec::new();
+    while !rest.is_empty() {
+        let (string, after) = parse_string(rest)?;
+        values.push(string);
+        rest = after.trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after.trim_start();
+        } else if !rest.is_empty() {
+            return None;
+        }
+    }
+    Some(values)
+}
+
+/// Reads a quoted string from the start of `value`, returning it and whatever comes after it
+fn parse_string(value: &str) -> Option<(String, &str)> {
+    let value = value.strip_prefix('"')?;
+    let end = value.find('"')?;
+    Some((value[..end].to_string(), &value[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line `Config::parse` says is wrong in `text`, if it says a line is
+    fn bad_line(text: &str) -> Option<usize> {
+        match Config::parse(text) {
+            Err(ConfigError::Syntax(line)) => Some(line),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn the_template_is_the_defaults() {
+        let config = Config::parse(TEMPLATE).unwrap();
+        assert_eq!(config.setup, Setup::default());
+        assert!(config.keys.is_empty());
+    }
+
+    #[test]
+    fn a_good_file_is_read() {
+        let text = "\
+# Comments and blank lines are skipped
+
+[board]
+width = 30    # the widest there is
+height = 16
+mines = 99
+layers = 2
+safe_opening = true
+
+[display]
+colours = false
+border = \"ascii\"
+
+[game]
+wrap = true
+
+[keys]
+layout = \"dvorak\"
+flag = [\"f\", \"enter\"]
+";
+        let config = Config::parse(text).unwrap();
+        let setup = config.setup;
+        assert_eq!((setup.width, setup.height, setup.depth), (30, 16, 2));
+        assert_eq!(setup.mines, 99);
+        assert_eq!(setup.preset, None);
+        assert!(setup.opening.clear_neighbours);
+        assert!(!config.settings.colours);
+        assert_eq!(config.settings.border, Border::Ascii);
+        assert!(config.settings.wrap_selection);
+        assert_eq!(config.layout.as_deref(), Some("dvorak"));
+        let flag = vec!["f".to_string(), "enter".to_string()];
+        assert_eq!(config.keys, vec![("flag".to_string(), flag)]);
+    }
+
+    #[test]
+    fn unknown_sections_and_names_are_left_alone() {
+        let text = "[future]\nwhatever = 3\n[board]\nshape = \"star\"\nwidth = 12\n";
+        assert_eq!(Config::parse(text).unwrap().setup.width, 12);
+    }
+
+    #[test]
+    fn bad_values_give_their_line() {
+        assert_eq!(bad_line("[board]\nwidth = 9\n\nheight = nine\n"), Some(4));
+        assert_eq!(bad_line("[board]\nwidth 9\n"), Some(2));
+        assert_eq!(bad_line("[board]\nwidth = [9, 10]\n"), Some(2));
+        assert_eq!(bad_line("[display]\ncolours = \"no\"\n"), Some(2));
+        assert_eq!(bad_line("[board]\npreset = \"impossible\"\n"), Some(2));
+        assert!(matches!(
+            Config::parse("[board]\nwidth = 0\n"),
+            Err(ConfigError::Board(_))
+        ));
+    }
+
+    #[test]
+    fn the_radius_has_to_be_one_a_grid_can_have() {
+        assert_eq!(bad_line("[board]\nradius = 0\n"), Some(2));
+        let too_far = format!("[board]\nradius = {}\n", MAX_RADIUS + 1);
+        assert_eq!(bad_line(&too_far), Some(2));
+        let furthest = format!("[board]\nradius = {}\n", MAX_RADIUS);
+        assert_eq!(Config::parse(&furthest).unwrap().setup.radius, MAX_RADIUS);
+    }
+
+    #[test]
+    fn anti_mines_are_a_percentage() {
+        assert_eq!(bad_line("[board]\n\nanti_mines = 101\n"), Some(3));
+        let config = Config::parse("[board]\nanti_mines = 100\n").unwrap();
+        assert_eq!(config.setup.anti_mines, 100);
+    }
+
+    #[test]
+    fn zen_can_be_turned_on_and_off() {
+        let zen = Config::parse("[board]\nzen = true\n").unwrap();
+        assert!(zen.setup.rules.is_zen());
+        let text = "[board]\nzen = true\nzen = false\ntime_limit = 60\n";
+        let rules = Config::parse(text).unwrap().setup.rules;
+        assert!(!rules.is_zen());
+        assert_eq!(rules.time_limit, Some(60));
+        assert_eq!(bad_line("[board]\nzen = 1\n"), Some(2));
+    }
+}
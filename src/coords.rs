@@ -0,0 +1,181 @@
+/// How rows and columns are written wherever the player reads or types them: the labels, the
+/// status bar, the go to prompt and the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    /// Rows and columns numbered from 0
+    ZeroBased,
+    /// Rows and columns numbered from 1
+    OneBased,
+    /// Columns lettered like a spreadsheet, A to Z and then AA, AB and so on, and rows numbered
+    /// from 1, so that a cell is written like `C7`
+    Letters,
+}
+
+impl Notation {
+    /// The name the notation goes by in the config file and on the settings screen
+    pub fn name(self) -> &'static str {
+        match self {
+            Notation::ZeroBased => "from 0",
+            Notation::OneBased => "from 1",
+            Notation::Letters => "letters",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Notation> {
+        [Notation::ZeroBased, Notation::OneBased, Notation::Letters]
+            .into_iter()
+            .find(|notation| notation.name() == name)
+    }
+
+    /// The notation after this one, coming back round to the first
+    pub fn next(self) -> Notation {
+        match self {
+            Notation::ZeroBased => Notation::OneBased,
+            Notation::OneBased => Notation::Letters,
+            Notation::Letters => Notation::ZeroBased,
+        }
+    }
+
+    pub fn row(self, row: usize) -> String {
+        match self {
+            Notation::ZeroBased => row.to_string(),
+            Notation::OneBased | Notation::Letters => (row + 1).to_string(),
+        }
+    }
+
+    pub fn col(self, col: usize) -> String {
+        match self {
+            Notation::ZeroBased => col.to_string(),
+            Notation::OneBased => (col + 1).to_string(),
+            Notation::Letters => column_letters(col),
+        }
+    }
+
+    /// A cell's position, like `(6,2)` or `C7`
+    pub fn position(self, row: usize, col: usize) -> String {
+        match self {
+            Notation::Letters => format!("{}{}", self.col(col), self.row(row)),
+            _ => format!("({},{})", self.row(row), self.col(col)),
+        }
+    }
+
+    /// What the go to prompt asks for
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Notation::Letters => "Go to cell",
+            _ => "Go to row and column",
+        }
+    }
+
+    /// Whether `c` can be typed into the go to prompt
+    pub fn accepts(self, c: char) -> bool {
+        match self {
+            Notation::Letters => c.is_ascii_alphanumeric(),
+            _ => c.is_ascii_digit() || c == ' ' || c == ',',
+        }
+    }
+
+    /// Reads a position written the way `position` writes it, or for numbers also like `12 34`
+    /// or `12,34`, as a row and column counting from 0. Nothing is checked against the size of
+    /// the board.
+    pub fn parse(self, input: &str) -> Option<(usize, usize)> {
+        if self == Notation::Letters {
+            let input = input.trim();
+            let split = input.find(|c: char| !c.is_ascii_alphabetic())?;
+            let (letters, digits) = input.split_at(split);
+            let col = letters_column(letters)?;
+            let row = digits.parse::<usize>().ok()?.checked_sub(1)?;
+            return Some((row, col));
+        }
+        let mut numbers = input
+            .split([' ', ',', '(', ')'])
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse::<usize>());
+        let row = numbers.next()?.ok()?;
+        let col = numbers.next()?.ok()?;
+        if numbers.next().is_some() {
+            return None;
+        }
+        match self {
+            Notation::ZeroBased => Some((row, col)),
+            _ => Some((row.checked_sub(1)?, col.checked_sub(1)?)),
+        }
+    }
+}
+
+/// The letters for a column counting from 0, so 0 is `A`, 25 is `Z` and 26 is `AA`
+fn column_letters(col: usize) -> String {
+    let mut letters = Vec::new();
+    let mut rest = col + 1;
+    while rest > 0 {
+        rest -= 1;
+        letters.push((b'A' + (rest % 26) as u8) as char);
+        rest /= 26;
+    }
+    letters.into_iter().rev().collect()
+}
+
+/// The column counting from 0 that `letters` stand for, in either case
+fn letters_column(letters: &str) -> Option<usize> {
+    if letters.is_empty() {
+        return None;
+    }
+    let mut col: usize = 0;
+    for c in letters.chars() {
+        let value = (c.to_ascii_uppercase() as u8).checked_sub(b'A')? as usize + 1;
+        if value > 26 {
+            return None;
+        }
+        col = col.checked_mul(26)?.checked_add(value)?;
+    }
+    Some(col - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_lettered_like_a_spreadsheet() {
+        assert_eq!(column_letters(0), "A");
+        assert_eq!(column_letters(25), "Z");
+        assert_eq!(column_letters(26), "AA");
+        assert_eq!(column_letters(27), "AB");
+        assert_eq!(column_letters(51), "AZ");
+        assert_eq!(column_letters(52), "BA");
+        assert_eq!(column_letters(701), "ZZ");
+        assert_eq!(column_letters(702), "AAA");
+    }
+
+    #[test]
+    fn letters_read_back_as_the_same_column() {
+        for col in 0..2000 {
+            assert_eq!(letters_column(&column_letters(col)), Some(col));
+        }
+        assert_eq!(letters_column("ab"), Some(27));
+        assert_eq!(letters_column(""), None);
+        assert_eq!(letters_column("A1"), None);
+    }
+
+    #[test]
+    fn positions_read_back_as_the_same_cell() {
+        let notations = [Notation::ZeroBased, Notation::OneBased, Notation::Letters];
+        for notation in notations {
+            for (row, col) in [(0, 0), (6, 2), (15, 25), (15, 26), (99, 27), (999, 999)] {
+                let position = notation.position(row, col);
+                assert_eq!(notation.parse(&position), Some((row, col)), "{}", position);
+            }
+        }
+    }
+
+    #[test]
+    fn positions_can_be_typed_in_other_ways() {
+        assert_eq!(Notation::ZeroBased.parse("12 34"), Some((12, 34)));
+        assert_eq!(Notation::OneBased.parse("12,34"), Some((11, 33)));
+        assert_eq!(Notation::Letters.parse(" aa10 "), Some((9, 26)));
+        assert_eq!(Notation::OneBased.parse("0 1"), None);
+        assert_eq!(Notation::ZeroBased.parse("1 2 3"), None);
+        assert_eq!(Notation::Letters.parse("C0"), None);
+        assert_eq!(Notation::Letters.parse("7"), None);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::timer::format_duration;
+use crate::title::{Difficulty, Setup, DIFFICULTIES};
+use std::fmt::Display;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The difficulty every daily board is played on
+pub const DAILY_DIFFICULTY: &Difficulty = &DIFFICULTIES[1];
+
+/// A day of the calendar, in UTC so that everyone has the same day at the same time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Date::from_days((seconds / 86400) as i64)
+    }
+
+    /// The date a number of days after 1970-01-01
+    pub fn from_days(days: i64) -> Date {
+        // Counted in 400 year eras starting from March, so that the leap day comes last
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+        let month = if month_from_march < 10 {
+            month_from_march + 3
+        } else {
+            month_from_march - 9
+        } as u32;
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+        Date { year, month, day }
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The seed of the board for `date`. It depends on nothing but the date, so that everyone gets
+/// the same board, and the bits are mixed up so that one day's board is nothing like the next.
+pub fn daily_seed(date: Date) -> u64 {
+    let mut seed = (date.year as u64) * 10000 + (date.month as u64) * 100 + date.day as u64;
+    // The finishing steps of splitmix64
+    seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+    seed = (seed ^ (seed >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    seed = (seed ^ (seed >> 27)).wrapping_mul(0x94d049bb133111eb);
+    seed ^= seed >> 31;
+    // Kept as short as random seeds are
+    seed >> 32
+}
+
+/// The board for `date`
+pub fn daily_setup(date: Date) -> Setup {
+    Setup {
+        seed: Some(daily_seed(date)),
+        ..DAILY_DIFFICULTY.setup(&Setup::default())
+    }
+}
+
+/// Where the result of each day's first game is kept, following the XDG base directory spec
+fn results_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".local")
+            .join("share"),
+    };
+    Some(base.join("minesweeper").join("daily.txt"))
+}
+
+/// Whether a result has been kept for `date` already, which makes any more games that day
+/// practice
+pub fn has_result(date: Date) -> bool {
+    let day = date.to_string();
+    results_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .is_some_and(|text| {
+            text.lines()
+                .any(|line| line.split(' ').next() == Some(&day))
+        })
+}
+
+/// Keeps the result of the game for `date`, a line for each day like `2024-06-01 won 3:07`
+pub fn record_result(date: Date, won: bool, time: Duration) -> std::io::Result<()> {
+    let path = results_path().ok_or(std::io::ErrorKind::NotFound)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let result = if won { "won" } else { "lost" };
+    writeln!(file, "{} {} {}", date, result, format_duration(time))
+}
+
+/// A daily board being played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Daily {
+    pub date: Date,
+    /// Whether the day's result has already been kept, so that this game doesn't count
+    pub practice: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i64, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn days_are_counted_from_1970() {
+        assert_eq!(Date::from_days(0), date(1970, 1, 1));
+        assert_eq!(Date::from_days(-1), date(1969, 12, 31));
+        assert_eq!(Date::from_days(11016), date(2000, 2, 29));
+        assert_eq!(Date::from_days(19782), date(2024, 2, 29));
+        assert_eq!(Date::from_days(19783), date(2024, 3, 1));
+        assert_eq!(Date::from_days(20740), date(2026, 10, 14));
+        assert_eq!(date(2024, 2, 29).to_string(), "2024-02-29");
+    }
+
+    #[test]
+    fn every_day_after_the_last_is_the_next_one() {
+        let mut last = Date::from_days(-800);
+        for days in -799..40000 {
+            let next = Date::from_days(days);
+            let following = next.day == last.day + 1 && next.month == last.month;
+            let new_month = next.day == 1 && (next.month == last.month % 12 + 1);
+            assert!(following || new_month, "{} came after {}", next, last);
+            last = next;
+        }
+    }
+
+    /// Changing these would give everyone a different board from the one they already played
+    #[test]
+    fn daily_seeds_stay_the_same() {
+        assert_eq!(daily_seed(date(1970, 1, 1)), 1693447888);
+        assert_eq!(daily_seed(date(2000, 2, 29)), 825524839);
+        assert_eq!(daily_seed(date(2024, 2, 29)), 691847210);
+        assert_eq!(daily_seed(date(2024, 3, 1)), 2756258336);
+        assert_eq!(daily_seed(date(2026, 10, 14)), 967214476);
+    }
+
+    #[test]
+    fn daily_boards_use_the_day_and_the_daily_difficulty() {
+        let setup = daily_setup(date(2024, 2, 29));
+        assert_eq!(setup.seed, Some(691847210));
+        assert_eq!(setup.preset, Some(DAILY_DIFFICULTY.name));
+        assert_eq!((setup.width, setup.mines), (16, 40));
+    }
+}
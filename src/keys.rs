@@ -0,0 +1,600 @@
+use crate::board::Direction;
+use std::time::{Duration, Instant};
+use termion::event::Key;
+
+/// Something the player can do by pressing a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    /// Opens the menu over the board
+    Menu,
+    Restart,
+    Pause,
+    Retry,
+    Undo,
+    Redo,
+    Move(Direction),
+    /// Moves several cells at once
+    MoveFast(Direction),
+    MoveToEdge(Direction),
+    /// Moves to the corner between two edges
+    MoveToCorner(Direction, Direction),
+    /// Opens a prompt for typing the position to move to
+    GoTo,
+    /// Moves to the closest cell that still needs dealing with
+    NearestCovered,
+    /// Moves to the next flag in reading order
+    NextFlag,
+    PreviousFlag,
+    /// Shows the layer below on a layered board, keeping the same row and column selected
+    NextLayer,
+    PreviousLayer,
+    Reveal,
+    Chord,
+    /// Chords every satisfied number on the board
+    ChordAll,
+    Flag,
+    Question,
+    /// Starts or stops recording a macro
+    Record,
+    /// Plays the recorded macro on the same board from the start
+    PlayMacro,
+    /// Clears the screen and draws everything again
+    Redraw,
+    /// Shows or hides the overview of the whole board
+    Minimap,
+    /// Turns the highlight around the selected cell on or off
+    Neighbourhood,
+    /// Turns the highlight along the selected cell's row and column on or off
+    Crosshair,
+    /// Shows which cells chording the selected number would open, until something else happens
+    ChordPreview,
+    /// Shows or hides the list of the most recent moves
+    Log,
+    /// Switches between compact cells and cells with space around them
+    Zoom,
+    /// Shows every action and the keys it is bound to
+    Help,
+    /// Switches numbers between how many mines they touch and how many are left to flag
+    EffectiveNumbers,
+}
+
+/// The groups actions are listed in on the help screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Movement,
+    /// What can be done to cells
+    Cells,
+    Game,
+}
+
+impl Section {
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Movement => "Movement",
+            Section::Cells => "Cells",
+            Section::Game => "Game",
+        }
+    }
+}
+
+/// The names actions go by in the config file. Fast moves and corners come from modified keys
+/// that can't be rebound, so they aren't here.
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("menu", Action::Menu),
+    ("restart", Action::Restart),
+    ("pause", Action::Pause),
+    ("retry", Action::Retry),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("move_up", Action::Move(Direction::Up)),
+    ("move_left", Action::Move(Direction::Left)),
+    ("move_down", Action::Move(Direction::Down)),
+    ("move_right", Action::Move(Direction::Right)),
+    ("move_up_left", Action::Move(Direction::UpLeft)),
+    ("move_up_right", Action::Move(Direction::UpRight)),
+    ("move_down_left", Action::Move(Direction::DownLeft)),
+    ("move_down_right", Action::Move(Direction::DownRight)),
+    ("jump_up", Action::MoveToEdge(Direction::Up)),
+    ("jump_left", Action::MoveToEdge(Direction::Left)),
+    ("jump_down", Action::MoveToEdge(Direction::Down)),
+    ("jump_right", Action::MoveToEdge(Direction::Right)),
+    ("goto", Action::GoTo),
+    ("nearest_covered", Action::NearestCovered),
+    ("next_flag", Action::NextFlag),
+    ("previous_flag", Action::PreviousFlag),
+    ("next_layer", Action::NextLayer),
+    ("previous_layer", Action::PreviousLayer),
+    ("reveal", Action::Reveal),
+    ("chord", Action::Chord),
+    ("chord_all", Action::ChordAll),
+    ("flag", Action::Flag),
+    ("question", Action::Question),
+    ("record_macro", Action::Record),
+    ("play_macro", Action::PlayMacro),
+    ("redraw", Action::Redraw),
+    ("minimap", Action::Minimap),
+    ("neighbourhood", Action::Neighbourhood),
+    ("crosshair", Action::Crosshair),
+    ("chord_preview", Action::ChordPreview),
+    ("zoom", Action::Zoom),
+    ("log", Action::Log),
+    ("help", Action::Help),
+    ("effective_numbers", Action::EffectiveNumbers),
+];
+
+impl Action {
+    /// Looks up an action by its name in the config file
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTION_NAMES
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|&(_, action)| action)
+    }
+
+    /// Where the action is listed on the help screen
+    pub fn section(self) -> Section {
+        match self {
+            Action::Move(_)
+            | Action::MoveFast(_)
+            | Action::MoveToEdge(_)
+            | Action::MoveToCorner(..)
+            | Action::GoTo
+            | Action::NearestCovered
+            | Action::NextFlag
+            | Action::PreviousFlag
+            | Action::NextLayer
+            | Action::PreviousLayer => Section::Movement,
+            Action::Reveal
+            | Action::Chord
+            | Action::ChordAll
+            | Action::Flag
+            | Action::Question
+            | Action::ChordPreview => Section::Cells,
+            _ => Section::Game,
+        }
+    }
+
+    /// The name of the action in the config file
+    pub fn name(self) -> String {
+        match ACTION_NAMES.iter().find(|(_, action)| *action == self) {
+            Some((name, _)) => name.to_string(),
+            None => format!("{:?}", self).to_lowercase(),
+        }
+    }
+}
+
+/// The letter and punctuation keys of a QWERTY keyboard, row by row
+const QWERTY: &str = "qwertyuiop[]asdfghjkl;'zxcvbnm,./";
+
+/// Keyboard layouts by name, each with the characters found where QWERTY has `QWERTY`. Picking a
+/// layout moves every binding to a letter to the same place on that layout's keyboard, so hjkl and
+/// wasd keep their shape.
+const LAYOUTS: &[(&str, &str)] = &[
+    ("qwerty", QWERTY),
+    ("dvorak", "',.pyfgcrl/=aoeuidhtns-;qjkxbmwvz"),
+    ("colemak", "qwfpgjluy;[]arstdhneio'zxcvbkm,./"),
+    ("azerty", "azertyuiop^$qsdfghjklmùwxcvbn,;:!"),
+];
+
+/// Which key triggers which action. Every key press goes through here, so a key bound to two
+/// actions shows up in `conflicts` rather than being decided by the order of `match` arms.
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let bindings = vec![
+            (Key::Ctrl('c'), Action::Quit),
+            (Key::Char('q'), Action::Quit),
+            (Key::Esc, Action::Menu),
+            (Key::Char('r'), Action::Restart),
+            (Key::Char('p'), Action::Pause),
+            (Key::Char('n'), Action::Retry),
+            (Key::Char('u'), Action::Undo),
+            (Key::Char('U'), Action::Redo),
+            (Key::Ctrl('r'), Action::Redo),
+            (Key::Up, Action::Move(Direction::Up)),
+            (Key::Left, Action::Move(Direction::Left)),
+            (Key::Down, Action::Move(Direction::Down)),
+            (Key::Right, Action::Move(Direction::Right)),
+            (Key::Char('k'), Action::Move(Direction::Up)),
+            (Key::Char('h'), Action::Move(Direction::Left)),
+            (Key::Char('j'), Action::Move(Direction::Down)),
+            (Key::Char('l'), Action::Move(Direction::Right)),
+            (Key::Char('w'), Action::Move(Direction::Up)),
+            (Key::Char('a'), Action::Move(Direction::Left)),
+            (Key::Char('s'), Action::Move(Direction::Down)),
+            (Key::Char('d'), Action::Move(Direction::Right)),
+            // The keys either side of u, without and with shift, for the diagonals a hex grid
+            // needs to reach every neighbour
+            (Key::Char('y'), Action::Move(Direction::UpLeft)),
+            (Key::Char('i'), Action::Move(Direction::UpRight)),
+            (Key::Char('Y'), Action::Move(Direction::DownLeft)),
+            (Key::Char('I'), Action::Move(Direction::DownRight)),
+            (Key::Char('K'), Action::MoveToEdge(Direction::Up)),
+            (Key::Char('H'), Action::MoveToEdge(Direction::Left)),
+            (Key::Char('J'), Action::MoveToEdge(Direction::Down)),
+            (Key::Char('L'), Action::MoveToEdge(Direction::Right)),
+            (Key::Home, Action::MoveToEdge(Direction::Left)),
+            (Key::End, Action::MoveToEdge(Direction::Right)),
+            (Key::PageUp, Action::MoveToEdge(Direction::Up)),
+            (Key::PageDown, Action::MoveToEdge(Direction::Down)),
+            (Key::Char('g'), Action::GoTo),
+            (Key::Char('o'), Action::NearestCovered),
+            (Key::Char('\t'), Action::NextFlag),
+            (Key::BackTab, Action::PreviousFlag),
+            (Key::Char('>'), Action::NextLayer),
+            (Key::Char(']'), Action::NextLayer),
+            (Key::Char('<'), Action::PreviousLayer),
+            (Key::Char('['), Action::PreviousLayer),
+            (Key::Char(' '), Action::Reveal),
+            (Key::Char('c'), Action::Chord),
+            (Key::Char('x'), Action::ChordAll),
+            (Key::Char('\n'), Action::Flag),
+            (Key::Char('f'), Action::Flag),
+            (Key::Char('m'), Action::Question),
+            (Key::Char('Q'), Action::Record),
+            (Key::Char('@'), Action::PlayMacro),
+            (Key::Ctrl('l'), Action::Redraw),
+            (Key::Char('v'), Action::Minimap),
+            (Key::Char('b'), Action::Neighbourhood),
+            (Key::Char('+'), Action::Crosshair),
+            (Key::Char('z'), Action::ChordPreview),
+            (Key::Char('-'), Action::Zoom),
+            (Key::Char('e'), Action::Log),
+            (Key::Char('?'), Action::Help),
+            (Key::F(1), Action::Help),
+            (Key::Char('t'), Action::EffectiveNumbers),
+        ];
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default keymap moved onto another keyboard layout, or `None` if there is no layout
+    /// with that name
+    pub fn for_layout(name: &str) -> Option<Keymap> {
+        let (_, layout) = LAYOUTS
+            .iter()
+            .find(|(layout, _)| layout.eq_ignore_ascii_case(name))?;
+        let translate = |c: char| {
+            let lower = c.to_ascii_lowercase();
+            match QWERTY.chars().position(|qwerty| qwerty == lower) {
+                Some(position) => {
+                    let moved = layout.chars().nth(position).unwrap_or(c);
+                    // Shifted punctuation is a different character, so capitals that land on
+                    // punctuation stay where they are
+                    match c.is_ascii_uppercase() {
+                        true if moved.is_alphabetic() => moved.to_uppercase().next().unwrap_or(c),
+                        true => c,
+                        false => moved,
+                    }
+                }
+                None => c,
+            }
+        };
+        let mut keymap = Keymap::default();
+        for (key, _) in keymap.bindings.iter_mut() {
+            if let Key::Char(c) = *key {
+                *key = Key::Char(translate(c));
+            }
+        }
+        Some(keymap)
+    }
+
+    /// The action bound to a key, if there is one
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|&(_, action)| action)
+    }
+
+    /// Binds an action to the given keys in place of the keys it had before
+    pub fn rebind(&mut self, action: Action, keys: &[Key]) {
+        self.bindings.retain(|&(_, bound)| bound != action);
+        self.bindings.extend(keys.iter().map(|&key| (key, action)));
+    }
+
+    /// Keys that are bound to more than one action, along with the first two actions they are bound
+    /// to
+    pub fn conflicts(&self) -> Vec<(Key, Action, Action)> {
+        let mut conflicts: Vec<(Key, Action, Action)> = Vec::new();
+        for (index, &(key, action)) in self.bindings.iter().enumerate() {
+            let clash = self.bindings[..index]
+                .iter()
+                .find(|&&(other_key, other_action)| other_key == key && other_action != action);
+            if let Some(&(_, other_action)) = clash {
+                if !conflicts.iter().any(|&(conflict, _, _)| conflict == key) {
+                    conflicts.push((key, other_action, action));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// A line for every action that can be bound, with the keys it is bound to, under a heading
+    /// for each section. This comes from the same table as the config file's names, so no action
+    /// can be left out.
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for section in [Section::Movement, Section::Cells, Section::Game] {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(section.title().to_string());
+            for &(name, action) in ACTION_NAMES {
+                if action.section() != section {
+                    continue;
+                }
+                let keys = match self.describe(action) {
+                    keys if keys.is_empty() => "not bound".to_string(),
+                    keys => keys,
+                };
+                lines.push(format!("  {:<16} {}", name.replace('_', " "), keys));
+            }
+            // The modified keys are fixed, so they aren't in the table
+            if section == Section::Movement {
+                lines.push(format!("  {:<16} {}", "move fast", "shift/ctrl-arrows"));
+                lines.push(format!("  {:<16} {}", "corners", "ctrl-home/ctrl-end"));
+            }
+        }
+        lines
+    }
+
+    /// The keys bound to an action, for showing in help text
+    pub fn describe(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|&&(_, bound)| bound == action)
+            .map(|&(key, _)| key_name(key))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// The action for an arrow, Home or End key pressed with Shift or Ctrl held down. Termion doesn't
+/// know these, so they arrive as the raw escape sequence like `ESC [ 1 ; 2 D`.
+pub fn modified_key_action(bytes: &[u8]) -> Option<Action> {
+    let (modifier, key) = match bytes {
+        [0x1b, b'[', b'1', b';', modifier, key] => (*modifier, *key),
+        _ => return None,
+    };
+    // 2 is Shift and 5 is Ctrl
+    if modifier != b'2' && modifier != b'5' {
+        return None;
+    }
+    match key {
+        b'A' => Some(Action::MoveFast(Direction::Up)),
+        b'B' => Some(Action::MoveFast(Direction::Down)),
+        b'C' => Some(Action::MoveFast(Direction::Right)),
+        b'D' => Some(Action::MoveFast(Direction::Left)),
+        b'H' if modifier == b'5' => Some(Action::MoveToCorner(Direction::Up, Direction::Left)),
+        b'F' if modifier == b'5' => Some(Action::MoveToCorner(Direction::Down, Direction::Right)),
+        _ => None,
+    }
+}
+
+/// Speeds up a movement key that is held down, or pressed quickly over and over. After `after`
+/// presses in the same direction, each no more than `window` apart, the key moves 2 cells at a
+/// time, then 4 after as many again.
+pub struct Acceleration {
+    pub enabled: bool,
+    pub after: usize,
+    pub window: Duration,
+    /// The direction and time of the last movement key
+    last: Option<(Direction, Instant)>,
+    /// How many presses in a row have come quickly enough
+    streak: usize,
+}
+
+impl Acceleration {
+    pub fn new(enabled: bool, after: usize, window: Duration) -> Acceleration {
+        Acceleration {
+            enabled,
+            after: after.max(1),
+            window,
+            last: None,
+            streak: 0,
+        }
+    }
+
+    /// How many cells a press of a movement key in the given direction moves the selection
+    pub fn steps(&mut self, direction: Direction) -> usize {
+        if !self.enabled {
+            return 1;
+        }
+        let now = Instant::now();
+        let repeated =
+            matches!(self.last, Some((last, at)) if last == direction && now - at <= self.window);
+        self.streak = if repeated { self.streak + 1 } else { 0 };
+        self.last = Some((direction, now));
+        match self.streak / self.after {
+            0 => 1,
+            1 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Forgets the last press, so the next one starts slow again
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.streak = 0;
+    }
+}
+
+/// Reads a key written the way `key_name` writes them, also accepting the arrow keys by name
+pub fn parse_key(name: &str) -> Option<Key> {
+    let key = match name {
+        "space" => Key::Char(' '),
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "shift-tab" => Key::BackTab,
+        "esc" => Key::Esc,
+        "backspace" => Key::Backspace,
+        "up" | "\u{2191}" => Key::Up,
+        "down" | "\u{2193}" => Key::Down,
+        "left" | "\u{2190}" => Key::Left,
+        "right" | "\u{2192}" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pgup" => Key::PageUp,
+        "pgdn" => Key::PageDown,
+        _ if name.len() > 1 && name.starts_with('f') => Key::F(name[1..].parse().ok()?),
+        _ => {
+            let (modifier, rest) = match name.split_once('-') {
+                Some((modifier, rest)) if !rest.is_empty() => (Some(modifier), rest),
+                _ => (None, name),
+            };
+            let mut chars = rest.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            match modifier {
+                None => Key::Char(c),
+                Some("ctrl") => Key::Ctrl(c),
+                Some("alt") => Key::Alt(c),
+                Some(_) => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+/// A short name for a key that fits in the status line
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char(' ') => "space".to_string(),
+        Key::Char('\n') => "enter".to_string(),
+        Key::Char('\t') => "tab".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("ctrl-{}", c),
+        Key::Alt(c) => format!("alt-{}", c),
+        Key::Left => "\u{2190}".to_string(),
+        Key::Up => "\u{2191}".to_string(),
+        Key::Right => "\u{2192}".to_string(),
+        Key::Down => "\u{2193}".to_string(),
+        Key::Esc => "esc".to_string(),
+        Key::PageUp => "pgup".to_string(),
+        Key::PageDown => "pgdn".to_string(),
+        Key::Home => "home".to_string(),
+        Key::End => "end".to_string(),
+        Key::Backspace => "backspace".to_string(),
+        Key::BackTab => "shift-tab".to_string(),
+        Key::F(number) => format!("f{}", number),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_bound_to_two_actions_are_conflicts() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.conflicts(), Vec::new());
+        keymap.rebind(Action::Flag, &[Key::Char('q'), Key::Char('f')]);
+        assert_eq!(
+            keymap.conflicts(),
+            vec![(Key::Char('q'), Action::Quit, Action::Flag)]
+        );
+        // The same action twice on a key is only a repeat
+        keymap.rebind(Action::Flag, &[Key::Char('f'), Key::Char('f')]);
+        assert_eq!(keymap.conflicts(), Vec::new());
+    }
+
+    #[test]
+    fn layouts_move_keys_to_where_they_are_on_qwerty() {
+        let dvorak = Keymap::for_layout("Dvorak").unwrap();
+        let left = Action::Move(Direction::Left);
+        assert_eq!(dvorak.action(Key::Char('d')), Some(left));
+        assert_eq!(
+            dvorak.action(Key::Char('h')),
+            Some(Action::Move(Direction::Down))
+        );
+        assert_eq!(
+            dvorak.action(Key::Char('t')),
+            Some(Action::Move(Direction::Up))
+        );
+        assert_eq!(
+            dvorak.action(Key::Char('n')),
+            Some(Action::Move(Direction::Right))
+        );
+        assert_eq!(
+            dvorak.action(Key::Char('T')),
+            Some(Action::MoveToEdge(Direction::Up))
+        );
+        assert_eq!(dvorak.action(Key::Char('\'')), Some(Action::Quit));
+        // Shift and q is a capital, but shift and ' isn't, so the capital stays put
+        assert_eq!(dvorak.action(Key::Char('Q')), Some(Action::Record));
+        // Keys that aren't characters don't move
+        assert_eq!(dvorak.action(Key::Left), Some(left));
+        assert_eq!(dvorak.action(Key::Ctrl('c')), Some(Action::Quit));
+
+        let colemak = Keymap::for_layout("colemak").unwrap();
+        assert_eq!(colemak.action(Key::Char('h')), Some(left));
+        assert_eq!(
+            colemak.action(Key::Char('e')),
+            Some(Action::Move(Direction::Up))
+        );
+        assert_eq!(colemak.action(Key::Char(';')), Some(Action::Pause));
+        assert_eq!(colemak.action(Key::Char('L')), Some(Action::Redo));
+        assert_eq!(colemak.action(Key::Char('p')), Some(Action::Restart));
+        assert!(Keymap::for_layout("klingon").is_none());
+    }
+
+    #[test]
+    fn layouts_keep_the_default_keys_apart() {
+        for (name, _) in LAYOUTS {
+            assert_eq!(Keymap::for_layout(name).unwrap().conflicts(), Vec::new());
+        }
+    }
+
+    #[test]
+    fn key_names_read_back_as_the_same_key() {
+        let keys = [
+            Key::Char('a'),
+            Key::Char('Q'),
+            Key::Char('@'),
+            Key::Char(' '),
+            Key::Char('\n'),
+            Key::Char('\t'),
+            Key::Char('-'),
+            Key::Ctrl('r'),
+            Key::Alt('x'),
+            Key::Up,
+            Key::Down,
+            Key::Left,
+            Key::Right,
+            Key::Esc,
+            Key::PageUp,
+            Key::PageDown,
+            Key::Home,
+            Key::End,
+            Key::Backspace,
+            Key::BackTab,
+            Key::F(1),
+            Key::F(12),
+        ];
+        for key in keys {
+            assert_eq!(parse_key(&key_name(key)), Some(key), "{}", key_name(key));
+        }
+        assert_eq!(parse_key("up"), Some(Key::Up));
+        assert_eq!(parse_key("shift-a"), None);
+        assert_eq!(parse_key("ab"), None);
+        assert_eq!(parse_key(""), None);
+    }
+
+    #[test]
+    fn action_names_read_back_as_the_same_action() {
+        for &(name, action) in ACTION_NAMES {
+            assert_eq!(Action::from_name(name), Some(action));
+            assert_eq!(action.name(), name);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::VecDeque;
+
+/// The most entries the log remembers, more than any terminal is likely to have room for
+const MAX_ENTRIES: usize = 100;
+
+/// How many columns the log pane takes up, not counting its frame
+pub const LOG_COLUMNS: usize = 28;
+
+/// The most lines the log pane takes up, not counting its frame
+pub const LOG_LINES: usize = 12;
+
+/// The most recent moves, described as they are made, so that what a chord or an assist did can
+/// be looked back over
+#[derive(Debug, Clone, Default)]
+pub struct ActionLog {
+    /// Oldest at the front
+    entries: VecDeque<String>,
+}
+
+impl ActionLog {
+    pub fn push(&mut self, entry: String) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// The newest entries that fit in `lines` lines, oldest first, cut or padded to exactly
+    /// `LOG_COLUMNS` columns. There are always `lines` of them, so the pane keeps its size as
+    /// the log fills up.
+    pub fn lines(&self, lines: usize) -> Vec<String> {
+        let skip = self.entries.len().saturating_sub(lines);
+        let mut shown: Vec<String> = self
+            .entries
+            .iter()
+            .skip(skip)
+            .map(|entry| {
+                let entry: String = entry.chars().take(LOG_COLUMNS).collect();
+                format!("{:<width$}", entry, width = LOG_COLUMNS)
+            })
+            .collect();
+        shown.resize(lines, " ".repeat(LOG_COLUMNS));
+        shown
+    }
+}
@@ -1,322 +1,2327 @@
+mod args;
+mod board;
+mod config;
+mod coords;
+mod daily;
+mod keys;
+mod log;
+mod menu;
+mod minimap;
+mod rules;
+mod screen;
+mod settings;
+mod solver;
+mod summary;
+mod terminal;
+mod theme;
+mod timer;
+mod title;
+mod topology;
+mod wave;
+
+use crate::args::Command;
+use crate::board::*;
+use crate::config::*;
+use crate::daily::*;
 use crate::io::stdin;
-use crate::io::stdout;
-use rand::*;
-use std::fmt::Display;
+use crate::keys::*;
+use crate::log::*;
+use crate::menu::*;
+use crate::minimap::*;
+use crate::rules::*;
+use crate::screen::*;
+use crate::settings::*;
+use crate::summary::*;
+use crate::timer::*;
+use crate::title::*;
+use crate::wave::*;
+use std::collections::VecDeque;
 use std::io;
-use std::io::Write;
-use termion::event::Key;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use termion::event::{Event, Key, MouseButton, MouseEvent};
 use termion::input::TermRead;
-use termion::raw::IntoRawMode;
+
+/// How many layouts are tried when looking for a board that can be finished without guessing
+const MAX_NO_GUESS_ATTEMPTS: usize = 500;
+
+/// How many layouts are tried between each update of the message saying how the search is going
+const NO_GUESS_PROGRESS_STEP: usize = 20;
+
+/// How little time a time attack has left before the countdown turns the warning colour
+const COUNTDOWN_WARNING: Duration = Duration::from_secs(30);
+
+/// What a no-flag game says when a flag or chord is tried
+const NO_FLAGS: &str = "Flags and chording are off in this game";
 
 pub struct Minesweeper {
     board: Board,
     first_move: bool,
+    state: GameState,
+    /// How mines are placed around the first reveal
+    opening: Opening,
+    /// Whether the mines are on the board yet, which waits for the first reveal when the
+    /// opening rules need it to
+    mines_placed: bool,
+    /// A short note shown under the status line until the next key press
+    message: Option<String>,
+    /// When the message was put up by `notify`, so that it can be highlighted and taken down
+    /// again after a while
+    notified: Option<Instant>,
+    /// Whether `notify` wants the bell rung. The next frame rings it, so that it goes out in
+    /// order with everything else drawn.
+    bell: std::cell::Cell<bool>,
+    /// Whether this game replays a board that was already played
+    retry: bool,
+    /// Whether moves can be taken back, which a scored mode would turn off
+    undo_enabled: bool,
+    /// How many mines can be set off in total before the game is lost
+    lives: usize,
+    /// The rules the game is played by. They are chosen with the board and can't change during
+    /// the game, so that a time played without flags, say, really was.
+    rules: Rules,
+    /// Whether the game was lost by the time limit running out
+    out_of_time: bool,
+    /// Whether the move being made found a mine that the rules forgive, so that it has to be
+    /// taken back when it ends
+    forgiven: bool,
+    /// The difficulty the board was picked from, if it wasn't a custom one, so that games can be
+    /// compared with others on the same difficulty
+    preset: Option<&'static str>,
+    /// The day this board is the daily board of, if it is one
+    daily: Option<Daily>,
+    /// Where the search for the nearest covered cell started and the last cell it found, so that
+    /// searching again moves on to the next one
+    nearest_search: Option<((usize, usize), (usize, usize))>,
+    /// A count typed before a movement key, which repeats the movement that many times
+    pending_count: Option<usize>,
+    /// The steps recorded so far while a macro is being recorded
+    recording: Option<Vec<MacroStep>>,
+    /// The last macro that was recorded
+    recorded: Vec<MacroStep>,
+    /// Whether the board or a menu is showing
+    ui: Ui,
+    /// An overview of which parts of the board have been cleared
+    minimap: Minimap,
+    /// Whether the minimap is drawn in the top right corner of the terminal
+    show_minimap: bool,
+    /// The moves made so far, described for the log pane
+    log: ActionLog,
+    /// Whether the log pane is drawn down the right of the terminal
+    show_log: bool,
+    /// What has been typed into the go to prompt while it is open
+    goto_input: Option<String>,
+    /// How long the game has been played for, counting from the first reveal
+    timer: Timer,
+    /// The most recent moves, newest at the back
+    undo_stack: VecDeque<Move>,
+    /// Moves that were undone and can be played again, newest at the back
+    redo_stack: Vec<Move>,
+    /// The cells the last move opened that are still being shown a ring at a time
+    wave: Option<Wave>,
+    /// What has been done so far this game, for the summary at the end
+    stats: Stats,
 }
 
-pub struct Board {
-    width: usize,
-    height: usize,
-    mines: usize,
-    cells: Vec<Cell>,
-    selected_row: usize,
-    selected_col: usize,
+/// One step of a recorded macro. Steps are actions rather than keys, so a macro still works after
+/// the keys are rebound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroStep {
+    /// An action along with the count it was given
+    Action(Action, usize),
+    /// A jump straight to a cell, from a click or the go to prompt
+    Select(usize, usize),
 }
 
-impl Display for Board {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut board = String::new();
-        for row in 0..self.height {
-            for col in 0..self.width {
-                if row == self.selected_row && col == self.selected_col {
-                    board.push_str(&format!("{}", termion::style::Bold));
-                }
-                board.push_str(&format!(
-                    " {}",
-                    &self.cells[row * self.width + col].to_string()
-                ));
-                if row == self.selected_row && col == self.selected_col {
-                    board.push_str(&format!("{}", termion::style::Reset));
+/// What the main loop waits on: something from the terminal, or a tick to keep the timer on
+/// the status bar up to date
+pub enum Input {
+    Event(Event),
+    Tick,
+    /// The terminal changed size
+    Resize,
+    /// The terminal's input was closed, so nothing more will arrive
+    Closed,
+}
+
+/// How often the timer on the status bar is checked for a new second to show
+const TICK_MS: u64 = 250;
+
+/// Starts reading the terminal on one thread and ticking on another, sending both to the
+/// returned channel so the main loop can wait on either. The ticking thread also watches the
+/// terminal's size, which saves catching SIGWINCH without a library for signals.
+fn spawn_input() -> Receiver<Input> {
+    let (sender, receiver) = mpsc::channel();
+    let events = sender.clone();
+    thread::spawn(move || {
+        for event in stdin().events() {
+            let event = match event {
+                Ok(event) => event,
+                Err(_) => break,
+            };
+            if events.send(Input::Event(event)).is_err() {
+                return;
+            }
+        }
+        let _ = events.send(Input::Closed);
+    });
+    thread::spawn(move || {
+        let mut size = termion::terminal_size().ok();
+        loop {
+            thread::sleep(Duration::from_millis(TICK_MS));
+            let resized = termion::terminal_size().ok();
+            if resized != size {
+                size = resized;
+                if sender.send(Input::Resize).is_err() {
+                    break;
                 }
             }
-            board.push_str("\r\n");
+            if sender.send(Input::Tick).is_err() {
+                break;
+            }
         }
-        // Remove the final newline
-        board.pop();
-        return write!(f, "{}", board);
-    }
+    });
+    receiver
 }
 
-pub struct Cell {
-    is_mine: bool,
-    is_revealed: bool,
-    is_flagged: bool,
-    adjacent_mines: i8,
-    row: usize,
-    col: usize,
+/// How long macro playback waits between steps when the config file doesn't say
+const DEFAULT_MACRO_DELAY_MS: u64 = 150;
+
+/// How long a spreading reveal waits between rings when the config file doesn't say
+const DEFAULT_REVEAL_DELAY_MS: u64 = 15;
+
+/// The most moves that are remembered for undoing, so huge boards don't use unbounded memory
+const MAX_UNDO: usize = 1000;
+
+/// How long the explanation for a key that did nothing stays up if no other key is pressed
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+/// Everything needed to take back a single reveal, chord or flag, however many cells it touched,
+/// or to play it again after it was undone
+pub struct Move {
+    changes: Vec<CellChange>,
+    state: GameState,
+    flags: usize,
+    correct_flags: usize,
+    revealed_mines: usize,
+    safe_cells_left: usize,
+    detonated: Option<usize>,
 }
 
-impl Display for Cell {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.is_revealed {
-            if self.is_mine {
-                return write!(f, "{}", "*");
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Playing,
+    Paused,
+    Won,
+    Lost,
+}
+
+impl Minesweeper {
+    /// Starts a game on a fresh board, placing its mines straight away unless they have to wait
+    /// for the first reveal
+    pub fn new(mut board: Board, opening: Opening) -> Minesweeper {
+        let mines_placed = !opening.is_deferred();
+        if mines_placed {
+            board.generate_mines(&[]);
+        }
+        Minesweeper::with_board(board, opening, mines_placed)
+    }
+
+    /// Starts a game on a board as it is, whose mines are already placed if `mines_placed` says
+    /// so
+    fn with_board(board: Board, opening: Opening, mines_placed: bool) -> Minesweeper {
+        Minesweeper {
+            minimap: Minimap::new(&board),
+            show_minimap: false,
+            log: ActionLog::default(),
+            show_log: false,
+            board,
+            first_move: true,
+            state: GameState::Playing,
+            opening,
+            mines_placed,
+            message: None,
+            notified: None,
+            bell: std::cell::Cell::new(false),
+            retry: false,
+            undo_enabled: true,
+            lives: 1,
+            rules: Rules::default(),
+            out_of_time: false,
+            forgiven: false,
+            preset: None,
+            daily: None,
+            nearest_search: None,
+            pending_count: None,
+            recording: None,
+            recorded: Vec::new(),
+            ui: Ui::Board,
+            goto_input: None,
+            timer: Timer::new(),
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            wave: None,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Abandons the current game and starts a new one with the same board size and options
+    pub fn restart(&mut self) {
+        let mut board = self.empty_board();
+        // The daily board comes out the same again, but once it's been seen it can't count
+        let daily = self.daily.map(|daily| Daily {
+            practice: daily.practice || !self.first_move,
+            ..daily
+        });
+        if let Some(daily) = daily {
+            board.seed(daily_seed(daily.date));
+        }
+        let mut game = Minesweeper::new(board, self.opening);
+        game.daily = daily;
+        self.start_over(game);
+    }
+
+    /// Plays the same mine layout again from the start
+    pub fn retry(&mut self) {
+        let mut board = self.empty_board();
+        board.seed(self.board.seed);
+        board.cells = std::mem::take(&mut self.board.cells);
+        board.safe_cells = self.board.safe_cells;
+        board.cover_all();
+        let mut game = Minesweeper::with_board(board, self.opening, true);
+        // The mines are already where they will stay, so the first move gets no special treatment
+        game.first_move = false;
+        game.retry = true;
+        game.daily = self.daily;
+        self.start_over(game);
+    }
+
+    /// A board with no mines yet, of the same size and with the same options as this game's
+    fn empty_board(&self) -> Board {
+        let mut board = Board::new_layered(
+            self.board.width,
+            self.board.height,
+            self.board.depth,
+            self.board.mines,
+            self.board.max_mines_per_cell,
+        )
+        .expect("The current board size is valid");
+        board.anti_mines = self.board.anti_mines;
+        board.fog = self.board.fog;
+        board.number_cap = self.board.number_cap;
+        board.topology = self.board.topology;
+        board.radius = self.board.radius;
+        board
+    }
+
+    /// Replaces this game with `game`, which gets the same rules and whatever can be changed
+    /// while playing. Nothing else is kept, so no message, log or search from this game shows
+    /// up in the next one.
+    fn start_over(&mut self, mut game: Minesweeper) {
+        game.lives = self.lives;
+        game.rules = Rules {
+            pause_countdown: self.board.settings.pause_countdown,
+            ..self.rules
+        };
+        game.preset = self.preset;
+        self.carry_settings(&mut game);
+        game.recorded = std::mem::take(&mut self.recorded);
+        *self = game;
+    }
+
+    /// Gives a new game the settings that can be changed while playing
+    pub fn carry_settings(&self, game: &mut Minesweeper) {
+        game.board.settings = self.board.settings.clone();
+        game.undo_enabled = self.undo_enabled;
+        game.show_minimap = self.show_minimap;
+        game.show_log = self.show_log;
+    }
+
+    /// The title screen choices this game was started with
+    pub fn setup(&self) -> Setup {
+        Setup {
+            width: self.board.width,
+            height: self.board.height,
+            mines: self.board.mines,
+            per_cell: self.board.max_mines_per_cell,
+            lives: self.lives,
+            opening: self.opening,
+            topology: self.board.topology,
+            radius: self.board.radius,
+            depth: self.board.depth,
+            anti_mines: self.board.anti_mines,
+            number_cap: self.board.number_cap.unwrap_or(
+                (self
+                    .board
+                    .topology
+                    .most_neighbours(self.board.radius, self.board.depth)
+                    * self.board.max_mines_per_cell as usize) as i16,
+            ),
+            preset: self.preset,
+            rules: self.rules,
+            // A new board shouldn't come out the same as this one
+            seed: None,
+        }
+    }
+
+    /// Pauses a game in progress, or resumes a paused one. This is also what losing focus of the
+    /// terminal should trigger. Returns `false` if the game is a time attack that can't be paused.
+    pub fn toggle_pause(&mut self) -> bool {
+        if self.state == GameState::Playing && !self.can_pause() {
+            return false;
+        }
+        self.state = match self.state {
+            GameState::Playing => GameState::Paused,
+            GameState::Paused => GameState::Playing,
+            state => state,
+        };
+        self.sync_timer();
+        true
+    }
+
+    /// Whether the game can be paused, which a time attack only allows when the settings let the
+    /// countdown stop as it started
+    pub fn can_pause(&self) -> bool {
+        self.rules.time_limit.is_none() || self.rules.pause_countdown
+    }
+
+    /// How long is left to clear the board in a time attack
+    pub fn time_left(&self) -> Option<Duration> {
+        let limit = self.rules.time_limit()?;
+        Some(limit.saturating_sub(self.timer.elapsed()))
+    }
+
+    /// Loses the game once a time attack's countdown has run out, uncovering the mines. Returns
+    /// whether that just happened.
+    pub fn check_time_limit(&mut self) -> bool {
+        if self.state != GameState::Playing || self.time_left() != Some(Duration::ZERO) {
+            return false;
+        }
+        self.state = GameState::Lost;
+        self.out_of_time = true;
+        self.timer.stop();
+        self.board.reveal_mines();
+        self.log.push("out of time".to_string());
+        true
+    }
+
+    /// Keeps the timer running while the game is being played, once the first reveal has started
+    /// it
+    fn sync_timer(&mut self) {
+        if self.state != GameState::Playing {
+            self.timer.stop();
+        } else if self.timer.has_started() {
+            self.timer.start();
+        }
+    }
+
+    /// Shows `ui` over the board, with the clock stopped until the board is back. Time attacks
+    /// that can't be paused keep counting down, as the board can still be seen around it.
+    fn cover_board(&mut self, ui: Ui) {
+        self.ui = ui;
+        if self.can_pause() {
+            self.timer.stop();
+        }
+    }
+
+    /// Notes how the game looks before a move so it can be undone afterwards
+    fn begin_move(&mut self) -> Move {
+        self.board.changes.clear();
+        Move {
+            changes: Vec::new(),
+            state: self.state,
+            flags: self.board.flags,
+            correct_flags: self.board.correct_flags,
+            revealed_mines: self.board.revealed_mines,
+            safe_cells_left: self.board.safe_cells_left,
+            detonated: self.board.detonated,
+        }
+    }
+
+    /// Remembers a finished move for undoing, unless it didn't change anything. A move that
+    /// found a forgiven mine is taken back instead.
+    fn end_move(&mut self, mut pending: Move) {
+        pending.changes = self.board.take_changes();
+        if self.forgiven {
+            self.forgiven = false;
+            self.apply(pending);
+            self.log.push("took the move back".to_string());
+            self.message = Some("That was a mine, so the move has been taken back".to_string());
+            return;
+        }
+        self.update_minimap(&pending.changes);
+        self.finish_wave();
+        if !self.board.settings.reduce_motion {
+            let board = &mut self.board;
+            let origin = board.selected_index();
+            self.wave = Wave::start(board, origin, &pending.changes);
+        }
+        if !self.undo_enabled || pending.changes.is_empty() {
+            return;
+        }
+        // A new move replaces whatever was undone before it
+        self.redo_stack.clear();
+        if self.undo_stack.len() == MAX_UNDO && !self.rules.unlimited_undo {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(pending);
+    }
+
+    /// A position on the layer being shown the way the player sees it, for the log
+    fn position(&self, row: usize, col: usize) -> String {
+        self.position_on(self.board.layer, row, col)
+    }
+
+    /// A position the way the player sees it, along with its layer when there are several
+    fn position_on(&self, layer: usize, row: usize, col: usize) -> String {
+        let position = self.board.settings.coordinates.position(row, col);
+        match self.board.depth {
+            1 => position,
+            _ => format!("{} on layer {}", position, layer + 1),
+        }
+    }
+
+    /// How the game went, for when it is over
+    pub fn summary(&self) -> Summary {
+        Summary {
+            won: self.state == GameState::Won,
+            time: self.timer.elapsed(),
+            width: self.board.width,
+            height: self.board.height,
+            depth: self.board.depth,
+            mines: self.board.mines,
+            anti_mines: self.board.anti_mines,
+            per_cell: self.board.max_mines_per_cell,
+            topology: self.board.topology,
+            radius: self.board.radius,
+            lives: self.lives,
+            preset: self.preset,
+            seed: self.board.seed,
+            rules: self.rules,
+            out_of_time: self.out_of_time,
+            cleared: self.board.cleared_percentage(),
+            stats: self.stats,
+        }
+    }
+
+    /// Tells the player that what they just tried couldn't be done, by ringing the bell and
+    /// flashing `message` on the message line, as the settings allow
+    pub fn notify(&mut self, message: &str) {
+        if self.board.settings.bell {
+            self.bell.set(true);
+        }
+        if self.board.settings.flash {
+            self.message = Some(message.to_string());
+            self.notified = Some(Instant::now());
+        }
+    }
+
+    /// Keeps the result of a daily game that counts, once it is over. Any more games on the same
+    /// board are practice.
+    fn record_daily(&mut self) {
+        let daily = match self.daily {
+            Some(daily) if !daily.practice && self.is_over() => daily,
+            _ => return,
+        };
+        self.daily = Some(Daily {
+            practice: true,
+            ..daily
+        });
+        let won = self.state == GameState::Won;
+        if let Err(error) = record_result(daily.date, won, self.timer.elapsed()) {
+            self.message = Some(format!("The daily result couldn't be kept: {}", error));
+        }
+    }
+
+    /// Takes down the message once it's been up for long enough, if `notify` put it there.
+    /// Returns whether it was taken down.
+    fn expire_notice(&mut self) -> bool {
+        match self.notified {
+            Some(since) if since.elapsed() >= NOTICE_DURATION => {
+                self.message = None;
+                self.notified = None;
+                true
             }
-            return write!(f, "{}", self.adjacent_mines);
-        } else if self.is_flagged {
-            return write!(f, "{}", "F");
+            _ => false,
         }
-        return write!(f, "{}", "\u{2022}");
     }
-}
 
-pub fn generate_cells(width: usize, height: usize) -> Vec<Cell> {
-    let mut cells = Vec::new();
-    for row in 0..height {
-        for col in 0..width {
-            cells.push(Cell {
-                is_mine: false,
-                is_revealed: false,
-                is_flagged: false,
-                adjacent_mines: 0,
-                row: row,
-                col: col,
-            });
+    /// Shows whatever is left of the last move's cells straight away
+    fn finish_wave(&mut self) {
+        if let Some(wave) = self.wave.take() {
+            wave.finish(&mut self.board);
         }
     }
-    return cells;
-}
 
-pub fn place_mines(cells: &mut Vec<Cell>, mines: usize) {
-    let mut rng = rand::thread_rng();
-    let mut mines_placed = 0;
-    while mines_placed < mines {
-        let index = rng.gen_range(0..cells.len());
-        if !cells[index].is_mine {
-            cells[index].is_mine = true;
-            mines_placed += 1;
+    /// Counts the cells that changed again in the minimap
+    fn update_minimap(&mut self, changes: &[CellChange]) {
+        for change in changes {
+            self.minimap.update(&self.board, change.index);
         }
     }
-}
 
-fn main() {
-    // Get the board size from the user
-    let mut width = String::new();
-    println!("Enter the width of the board: ");
-    io::stdin()
-        .read_line(&mut width)
-        .expect("Failed to read line");
-
-    let mut height = String::new();
-    println!("Enter the height of the board: ");
-    io::stdin()
-        .read_line(&mut height)
-        .expect("Failed to read line");
-
-    let mut mines = String::new();
-    println!("Enter the number of mines: ");
-    io::stdin()
-        .read_line(&mut mines)
-        .expect("Failed to read line");
-
-    // Create the board
-    let mut board = Board {
-        width: width
-            .trim()
-            .parse::<usize>()
-            .expect("Failed to parse width (did you provide a valid number)"),
-        height: height
-            .trim()
-            .parse::<usize>()
-            .expect("Failed to parse height (did you provide a valid number)"),
-        mines: mines
-            .trim()
-            .parse::<usize>()
-            .expect("Failed to parse mines (did you provide a valid number)"),
-        cells: vec![],
-        selected_row: 0,
-        selected_col: 0,
-    };
-    board.cells = generate_cells(board.width, board.height);
+    /// Takes back the most recent move, returning `false` if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let last = match self.undo_stack.pop_back() {
+            Some(last) => last,
+            None => return false,
+        };
+        let redo = self.apply(last);
+        self.redo_stack.push(redo);
+        true
+    }
 
-    // Place the mines
-    place_mines(&mut board.cells, board.mines);
+    /// Plays the most recently undone move again, returning `false` if there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let last = match self.redo_stack.pop() {
+            Some(last) => last,
+            None => return false,
+        };
+        let undo = self.apply(last);
+        self.undo_stack.push_back(undo);
+        true
+    }
 
-    // Calculate all adjacent mines
-    calculate_adjacent_mines(&mut board);
+    /// Puts the game back the way the move describes, returning the move that would reverse it
+    fn apply(&mut self, next: Move) -> Move {
+        let reverse = Move {
+            changes: self.board.current_state(&next.changes),
+            state: self.state,
+            flags: self.board.flags,
+            correct_flags: self.board.correct_flags,
+            revealed_mines: self.board.revealed_mines,
+            safe_cells_left: self.board.safe_cells_left,
+            detonated: self.board.detonated,
+        };
+        self.board.restore(&next.changes);
+        self.update_minimap(&next.changes);
+        self.state = next.state;
+        self.board.flags = next.flags;
+        self.board.correct_flags = next.correct_flags;
+        self.board.revealed_mines = next.revealed_mines;
+        self.board.safe_cells_left = next.safe_cells_left;
+        self.board.detonated = next.detonated;
+        // Taking back the end of a game carries on the clock
+        self.sync_timer();
+        reverse
+    }
 
-    // Use termion to detect when movement keys are pressed
+    /// Cycles the mark on the cell at the given position. With auto-chord on, placing a flag also
+    /// chords every number it satisfies as part of the same move.
+    pub fn toggle_flag(&mut self, row: usize, col: usize) -> Option<FlagResult> {
+        let pending = self.begin_move();
+        let flags = |board: &Board| cell_from_pos(row, col, board).map(|i| board.cells[i].flags());
+        let before = flags(&self.board);
+        let result = self.board.toggle_flag(row, col);
+        let after = flags(&self.board);
+        if after > before {
+            self.stats.flags_placed += 1;
+        }
+        if result == Some(FlagResult::Toggled) {
+            let index = cell_from_pos(row, col, &self.board).expect("The cell was toggled");
+            let verb = match self.board.cells[index].mark {
+                Mark::AntiFlag(_) => "anti-flag",
+                _ if after > before => "flag",
+                _ => "unflag",
+            };
+            self.log
+                .push(format!("{} {}", verb, self.position(row, col)));
+        }
+        if result == Some(FlagResult::Toggled) && self.board.settings.auto_chord {
+            let cell_index = cell_from_pos(row, col, &self.board).expect("The cell was toggled");
+            if self.board.cells[cell_index].is_flagged() {
+                match self.board.chord_satisfied_around(cell_index) {
+                    ChordResult::Unsatisfied => {}
+                    ChordResult::Opened => self.check_win(),
+                    ChordResult::Mine(mine) => self.hit_mine(mine),
+                }
+            }
+        }
+        self.run_assists();
+        self.end_move(pending);
+        result
+    }
+
+    /// Moves the selection to the nearest cell that is neither revealed nor flagged. Searching
+    /// again without moving steps further out from where the first search started, going back to
+    /// the nearest cell once every candidate has been visited. Returns `false` if there is no
+    /// other such cell.
+    pub fn select_nearest_covered(&mut self) -> bool {
+        let selected = (self.board.selected_row, self.board.selected_col);
+        let (origin, after) = match self.nearest_search {
+            Some((origin, last)) if last == selected => (origin, Some(last)),
+            _ => (selected, None),
+        };
+        let found = self
+            .board
+            .next_covered_cell(origin, after)
+            .or_else(|| self.board.next_covered_cell(origin, None));
+        match found {
+            Some(position) => {
+                (self.board.selected_row, self.board.selected_col) = position;
+                self.nearest_search = Some((origin, position));
+                true
+            }
+            None => false,
+        }
+    }
 
-    let stdin = stdin();
-    let mut stdout = stdout().into_raw_mode().unwrap();
+    /// Starts recording a macro, or stops and keeps the recording if one is going
+    pub fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(steps) => {
+                self.message = Some(format!("Recorded a macro of {} steps", steps.len()));
+                self.recorded = steps;
+            }
+            None => self.recording = Some(Vec::new()),
+        }
+    }
 
-    // Clear the screen and hide the cursor
-    write!(
-        stdout,
-        "{}{}{}",
-        termion::clear::All,
-        termion::cursor::Hide,
-        termion::cursor::Goto(1, 1)
-    )
-    .unwrap();
-    stdout.flush().unwrap();
-
-    let mut game = Minesweeper { board: board, first_move: true };
-
-    render(&mut game);
-    for c in stdin.keys() {
-        match c.unwrap() {
-            Key::Ctrl('c') | Key::Char('q') => break,
-            Key::Left => {
-                if game.board.selected_col > 0 {
-                    game.board.selected_col -= 1;
+    /// Adds a step to the macro being recorded, if there is one
+    pub fn record(&mut self, step: MacroStep) {
+        if let Some(steps) = self.recording.as_mut() {
+            steps.push(step);
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self.state, GameState::Won | GameState::Lost)
+    }
+
+    /// Whether there is a game going that quitting would throw away
+    pub fn in_progress(&self) -> bool {
+        self.state != GameState::Won && self.state != GameState::Lost && !self.first_move
+    }
+
+    /// Toggles a question mark on the cell at the given position as a single move
+    pub fn toggle_question(&mut self, row: usize, col: usize) -> Option<FlagResult> {
+        let pending = self.begin_move();
+        let result = self.board.toggle_question(row, col);
+        if result == Some(FlagResult::Toggled) {
+            self.log
+                .push(format!("question {}", self.position(row, col)));
+        }
+        self.end_move(pending);
+        result
+    }
+
+    /// Places the mines that were waiting for the first reveal at `cell_index`. Boards that
+    /// can't be finished without guessing are thrown away, up to a limit, and `progress` is
+    /// called now and then with the message saying how the search is going so it can be shown.
+    pub fn place_mines(&mut self, cell_index: usize, progress: &mut dyn FnMut(&Minesweeper)) {
+        if self.mines_placed {
+            return;
+        }
+        self.mines_placed = true;
+        if !self.opening.no_guess {
+            self.board
+                .place_mines_around_opening(cell_index, self.opening);
+            return;
+        }
+        if !solver::can_solve(&self.board) {
+            self.board
+                .place_mines_around_opening(cell_index, self.opening);
+            self.message = Some(
+                "Boards with anti-mines or mines sharing cells can't be checked for guessing"
+                    .to_string(),
+            );
+            return;
+        }
+        // The solver needs somewhere to start from, which an empty first reveal gives it
+        let opening = Opening {
+            clear_neighbours: true,
+            ..self.opening
+        };
+        for attempt in 1..=MAX_NO_GUESS_ATTEMPTS {
+            self.board.place_mines_around_opening(cell_index, opening);
+            if solver::is_solvable(&self.board, cell_index) {
+                if attempt > NO_GUESS_PROGRESS_STEP {
+                    self.message = None;
                 }
+                return;
+            }
+            if attempt % NO_GUESS_PROGRESS_STEP == 0 {
+                self.message = Some(format!(
+                    "Looking for a board without guessing: {} of {} tried",
+                    attempt, MAX_NO_GUESS_ATTEMPTS
+                ));
+                progress(self);
+            }
+        }
+        self.message =
+            Some("No board without guessing turned up, so this one may need a guess".to_string());
+    }
+
+    /// Reveals the cell at the given position, taking care of the first move and switching to
+    /// the won or lost state when the reveal decides the game
+    pub fn reveal(&mut self, row: usize, col: usize) -> Option<RevealResult> {
+        let cell_index = cell_from_pos(row, col, &self.board)?;
+        let cell = &self.board.cells[cell_index];
+        if self.first_move && !cell.is_flagged() {
+            if !self.mines_placed {
+                self.place_mines(cell_index, &mut |_| {});
+            } else if cell.is_mine() {
+                // Never lose on the very first reveal
+                self.board.relocate_mine(cell_index);
+            }
+        }
+        let pending = self.begin_move();
+        let safe_cells_left = self.board.safe_cells_left;
+        let result = self.board.reveal(row, col);
+        if matches!(result, Some(RevealResult::Safe) | Some(RevealResult::Mine)) {
+            self.timer.start();
+            self.stats.reveals += 1;
+            let opened = safe_cells_left - self.board.safe_cells_left;
+            self.stats.cascade_cells += opened.saturating_sub(1);
+            let position = self.position(row, col);
+            let number = self.board.cells[cell_index].adjacent_mines;
+            self.log.push(match opened {
+                0 => format!("reveal {}", position),
+                1 => format!("reveal {} \u{2192} {}", position, number),
+                opened => format!("reveal {} opened {} cells", position, opened),
+            });
+        }
+        match result {
+            Some(RevealResult::Mine) => self.hit_mine(cell_index),
+            Some(RevealResult::Safe) => {
+                self.first_move = false;
+                self.check_win();
+                self.move_mine();
             }
-            Key::Right => {
-                if game.board.selected_col < game.board.width - 1 {
-                    game.board.selected_col += 1;
+            Some(RevealResult::AlreadyRevealed) | Some(RevealResult::Flagged) | None => {}
+        }
+        self.run_assists();
+        self.end_move(pending);
+        result
+    }
+
+    /// With moving mines, moves one of them once enough reveals have gone by. Where it went is
+    /// left for the player to notice in the numbers.
+    fn move_mine(&mut self) {
+        let Some(every) = self.rules.moving_mines else {
+            return;
+        };
+        let due = self.stats.reveals.is_multiple_of(every);
+        if due && self.state == GameState::Playing && self.board.shift_mine() {
+            self.log.push("something shifted".to_string());
+            self.message = Some("Something shifted\u{2026}".to_string());
+        }
+    }
+
+    /// Chords on the cell at the given position, losing the game if a wrong flag lets a mine be
+    /// revealed. Returns `false` if the cell couldn't be chorded.
+    pub fn chord(&mut self, row: usize, col: usize) -> bool {
+        let pending = self.begin_move();
+        let before = self.board.changes.len();
+        let result = self.board.chord(row, col);
+        if result != ChordResult::Unsatisfied {
+            let opened = self.board.changes.len() - before;
+            let position = self.position(row, col);
+            self.log
+                .push(format!("chord {} opened {} cells", position, opened));
+        }
+        let chorded = match result {
+            ChordResult::Unsatisfied => false,
+            ChordResult::Opened => {
+                self.check_win();
+                true
+            }
+            ChordResult::Mine(cell_index) => {
+                self.hit_mine(cell_index);
+                true
+            }
+        };
+        if chorded {
+            self.stats.chords += 1;
+        }
+        self.run_assists();
+        self.end_move(pending);
+        chorded
+    }
+
+    /// Chords every satisfied number on the board as a single move, returning how many cells were
+    /// opened
+    pub fn chord_all(&mut self) -> usize {
+        let pending = self.begin_move();
+        let before = self.board.changes.len();
+        let result = self.board.chord_all();
+        let opened = self.board.changes.len() - before;
+        if opened > 0 {
+            self.stats.chords += 1;
+            self.log.push(format!("chord all opened {} cells", opened));
+        }
+        match result {
+            ChordResult::Unsatisfied => {}
+            ChordResult::Opened => self.check_win(),
+            ChordResult::Mine(cell_index) => self.hit_mine(cell_index),
+        }
+        self.run_assists();
+        self.end_move(pending);
+        opened
+    }
+
+    /// Runs the assists after a move, noting in the log what they did
+    fn run_assists(&mut self) {
+        let (flags, safe_cells_left) = (self.board.flags, self.board.safe_cells_left);
+        self.assist();
+        let flagged = self.board.flags.saturating_sub(flags);
+        let opened = safe_cells_left - self.board.safe_cells_left;
+        if flagged > 0 {
+            self.log.push(format!("assists flagged {} cells", flagged));
+        }
+        if opened > 0 {
+            self.log.push(format!("assists opened {} cells", opened));
+        }
+    }
+
+    /// Runs the enabled assists over every cell the current move has uncovered, repeating for as
+    /// long as they keep opening or flagging cells, then finishes the board if its flags leave
+    /// nothing to work out
+    fn assist(&mut self) {
+        let mut checked = 0;
+        // Assists that flag would be flagging for the player
+        while self.state == GameState::Playing
+            && self.board.settings.auto_flag
+            && !self.rules.no_flags
+        {
+            let start = self.board.changes.len();
+            let opened: Vec<usize> = self.board.changes[checked..]
+                .iter()
+                .map(|change| change.index)
+                .filter(|&index| self.board.cells[index].is_revealed)
+                .collect();
+            let flagged = self.board.flag_forced_around(&opened);
+            if self.board.settings.auto_chord {
+                for index in flagged {
+                    match self.board.chord_satisfied_around(index) {
+                        ChordResult::Unsatisfied => {}
+                        ChordResult::Opened => self.check_win(),
+                        ChordResult::Mine(mine) => {
+                            self.hit_mine(mine);
+                            return;
+                        }
+                    }
+                }
+            }
+            if self.board.changes.len() == start {
+                break;
+            }
+            checked = start;
+        }
+        if self.state == GameState::Playing && self.board.all_mines_flagged() {
+            // Nothing is left to work out, so finish the board off
+            self.board.reveal_safe_cells();
+            self.check_win();
+        }
+    }
+
+    /// How many more mines can be set off before the game is lost
+    pub fn lives_left(&self) -> usize {
+        self.lives.saturating_sub(self.board.revealed_mines)
+    }
+
+    /// Costs a life after the mine at `cell_index` was revealed, losing the game once there are
+    /// none left
+    fn hit_mine(&mut self, cell_index: usize) {
+        let cell = &self.board.cells[cell_index];
+        let position = self.position_on(cell.layer, cell.row, cell.col);
+        self.log.push(format!("BOOM at {}", position));
+        if self.rules.forgive_mines {
+            // `end_move` takes the move back once it is over
+            self.forgiven = true;
+        } else if self.lives_left() == 0 {
+            self.lose(cell_index);
+        } else {
+            self.message = Some(format!("Boom! Lives left: {}", self.lives_left()));
+        }
+    }
+
+    /// Switches to the lost state after the mine at `cell_index` was revealed, uncovering every
+    /// other mine on the board
+    pub fn lose(&mut self, cell_index: usize) {
+        self.state = GameState::Lost;
+        self.timer.stop();
+        self.board.detonated = Some(cell_index);
+        self.board.reveal_mines();
+    }
+
+    /// Switches to the won state once every safe cell has been revealed, flagging any mines that
+    /// were left unflagged so the final board is complete.
+    pub fn check_win(&mut self) {
+        if self.board.safe_cells_left > 0 {
+            return;
+        }
+        self.state = GameState::Won;
+        self.timer.stop();
+        self.board.flag_mines();
+    }
+}
+
+fn main() {
+    terminal::install_panic_hook();
+    // Problems with the config file are shown on the title screen
+    let mut notices = Vec::new();
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(error) => {
+            notices.push(format!("{}, so the default settings will be used", error));
+            Config::default()
+        }
+    };
+    // Nothing has been done to the terminal yet, so anything printed here comes out plainly
+    let args = match args::parse(std::env::args().skip(1), config.setup) {
+        Ok(Command::Play(args)) => *args,
+        Ok(Command::Print(text)) => {
+            println!("{}", text.trim_end());
+            return;
+        }
+        Err(error) => {
+            eprintln!("minesweeper: {}", error);
+            std::process::exit(2);
+        }
+    };
+    // NO_COLOR turns colours off whatever it is set to, as long as it isn't empty
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        config.settings.colours = false;
+    }
+    // Options given on the command line win over the config file
+    if let Some(layout) = args.layout {
+        config.layout = Some(layout);
+    }
+    if args.no_colour {
+        config.settings.colours = false;
+    }
+    if args.colourblind {
+        // Numbers get their own styles too, so that no colour has to be told apart
+        config
+            .use_theme("colourblind")
+            .expect("The colourblind theme is built in");
+        config.settings.number_styles = true;
+    }
+    let keymap = match config.keymap() {
+        Ok(keymap) => keymap,
+        Err(error) => {
+            notices.push(format!("{}, so the default keys will be used", error));
+            Keymap::default()
+        }
+    };
+    let macro_delay =
+        Duration::from_millis(config.macro_delay_ms.unwrap_or(DEFAULT_MACRO_DELAY_MS));
+    let reveal_delay =
+        Duration::from_millis(config.reveal_delay_ms.unwrap_or(DEFAULT_REVEAL_DELAY_MS));
+    let fast_step = config.fast_step.unwrap_or(FAST_MOVE_STEP);
+    let mut acceleration = Acceleration::new(
+        config.accelerate.unwrap_or(false),
+        config.accelerate_after.unwrap_or(ACCELERATE_AFTER),
+        Duration::from_millis(config.accelerate_window_ms.unwrap_or(ACCELERATE_WINDOW_MS)),
+    );
+
+    // Play on the alternate screen in raw mode, until the terminal is restored on the way out
+    terminal::enter().expect("The terminal couldn't be set up");
+    let inputs = spawn_input();
+    let mut screen = Screen::new(Box::new(io::stdout()));
+    // A board given on the command line skips the title screen
+    let outcome = match args.setup {
+        Some(setup) => TitleOutcome::Start(setup),
+        None => {
+            let mut title = TitleScreen::new(config.setup);
+            title.notices = notices.clone();
+            show_title(title, &inputs, &keymap, &mut screen, false)
+        }
+    };
+    let mut game = match outcome {
+        TitleOutcome::Start(setup) => new_game(&setup, &config),
+        _ => {
+            terminal::restore();
+            return;
+        }
+    };
+    if let Some(date) = args.daily {
+        // A zen game can't count, so it is practice from the start
+        let practice = has_result(date) || !game.rules.keep_results;
+        game.daily = Some(Daily { date, practice });
+        // Taking back a mistake would make the result mean nothing
+        game.undo_enabled = practice;
+    }
+    if args.setup.is_some() && !notices.is_empty() {
+        // There was no title screen to show them on
+        game.message = Some(notices.join(". "));
+    }
+
+    render(&game, &keymap, &mut screen);
+    let mut shown_seconds = 0;
+    // Input that cut a spreading reveal short, waiting to be handled
+    let mut held = None;
+    loop {
+        let input = match held.take() {
+            Some(input) => input,
+            None => match inputs.recv() {
+                Ok(input) => input,
+                Err(_) => break,
+            },
+        };
+        let event = match input {
+            Input::Event(event) => event,
+            Input::Tick => {
+                // The countdown runs out whether or not any keys are being pressed
+                if game.check_time_limit() {
+                    game.ui = Ui::GameOver(0);
+                    game.record_daily();
+                    render(&game, &keymap, &mut screen);
+                    continue;
+                }
+                // Only redraw when the time on the status bar has changed
+                let seconds = game.timer.elapsed().as_secs();
+                let expired = game.expire_notice();
+                if expired || game.timer.is_running() && seconds != shown_seconds {
+                    shown_seconds = seconds;
+                    render(&game, &keymap, &mut screen);
                 }
+                continue;
+            }
+            Input::Resize => {
+                // Whatever was on the screen has been rearranged by the terminal
+                screen.request_redraw();
+                render(&game, &keymap, &mut screen);
+                continue;
             }
-            Key::Up => {
-                if game.board.selected_row > 0 {
-                    game.board.selected_row -= 1;
+            Input::Closed => break,
+        };
+        // While a menu is open, keys work the menu instead of playing
+        match game.ui {
+            Ui::Board => {}
+            Ui::Menu(selected) => {
+                if let Event::Key(key) = event {
+                    match menu_input(key, &keymap, selected, MENU_ITEMS.len()) {
+                        MenuInput::Select(selected) => game.ui = Ui::Menu(selected),
+                        MenuInput::Close => game.ui = Ui::Board,
+                        MenuInput::Choose => {
+                            let playing = choose(
+                                MENU_ITEMS[selected],
+                                &mut game,
+                                &config,
+                                &keymap,
+                                &inputs,
+                                &mut screen,
+                            );
+                            if !playing {
+                                break;
+                            }
+                        }
+                        MenuInput::None => {}
+                    }
+                    if game.ui == Ui::Board {
+                        game.sync_timer();
+                    }
+                    render(&game, &keymap, &mut screen);
                 }
+                continue;
             }
-            Key::Down => {
-                if game.board.selected_row < game.board.height - 1 {
-                    game.board.selected_row += 1;
+            Ui::GameOver(selected) => {
+                if let Event::Key(key) = event {
+                    match menu_input(key, &keymap, selected, GAME_OVER_ITEMS.len()) {
+                        MenuInput::Select(selected) => game.ui = Ui::GameOver(selected),
+                        // Closing the box leaves the finished board to look over
+                        MenuInput::Close => game.ui = Ui::Board,
+                        MenuInput::Choose => {
+                            let (item, _) = GAME_OVER_ITEMS[selected];
+                            if !choose(item, &mut game, &config, &keymap, &inputs, &mut screen) {
+                                break;
+                            }
+                        }
+                        MenuInput::None => {}
+                    }
+                    render(&game, &keymap, &mut screen);
                 }
+                continue;
             }
-            Key::Char(' ') => {
-                let cell_index = cell_from_pos(
-                    game.board.selected_row as i8,
-                    game.board.selected_col as i8,
-                    &game.board,
-                )
-                .expect("Selected cell doesn't exist");
-                if game.board.cells[cell_index].is_mine {
-                    if game.first_move {
-                        game.board.cells[cell_index].is_mine = false;
-                        calculate_adjacent_mines(&mut game.board);
+            Ui::Help(page) => {
+                if let Event::Key(key) = event {
+                    // Escape closes it straight away, and anything else turns the page
+                    let pages = help_pages(&keymap).len();
+                    if key != Key::Esc && page + 1 < pages {
+                        game.ui = Ui::Help(page + 1);
                     } else {
-                        println!("You lost!");
+                        game.ui = Ui::Board;
+                        game.sync_timer();
+                    }
+                    render(&game, &keymap, &mut screen);
+                }
+                continue;
+            }
+            Ui::ConfirmQuit => {
+                if let Event::Key(key) = event {
+                    // Pressing Ctrl-C a second time quits as well, in case the prompt went unseen
+                    if matches!(key, Key::Char('y') | Key::Char('Y') | Key::Ctrl('c')) {
                         break;
                     }
+                    game.ui = Ui::Board;
+                    game.sync_timer();
+                    render(&game, &keymap, &mut screen);
                 }
-                let cell = &mut game.board.cells[cell_index];
-                if !cell.is_revealed {
-                    if cell.adjacent_mines == 0 {
-                        reveal_cells_around(&mut game.board, cell_index);
+                continue;
+            }
+            Ui::Settings(selected) => {
+                if let Event::Key(key) = event {
+                    // Saving and going back to the menu come after the settings
+                    let count = SETTINGS.len() + 2;
+                    let back = Ui::Menu(3);
+                    match menu_input(key, &keymap, selected, count) {
+                        MenuInput::Select(selected) => game.ui = Ui::Settings(selected),
+                        MenuInput::Close => game.ui = back,
+                        MenuInput::Choose if selected == count - 1 => game.ui = back,
+                        MenuInput::Choose if selected == count - 2 => {
+                            game.message = Some(
+                                match Config::save_settings(&game.board.settings, &game.opening) {
+                                    Ok(path) => format!("Settings saved to {}", path.display()),
+                                    Err(error) => error.to_string(),
+                                },
+                            );
+                        }
+                        MenuInput::Choose => {
+                            let setting = SETTINGS[selected];
+                            if !setting.needs_new_board() {
+                                setting.change(&mut game.board.settings, &config.all_themes());
+                            } else if game.in_progress() {
+                                game.notify("Finish or restart the game to change this");
+                            } else {
+                                let item = MenuItem::NewBoard;
+                                if !choose(item, &mut game, &config, &keymap, &inputs, &mut screen)
+                                {
+                                    break;
+                                }
+                            }
+                        }
+                        MenuInput::None => {}
+                    }
+                    if game.ui == Ui::Board {
+                        game.sync_timer();
                     }
-                    game.board.cells[cell_index].is_revealed = true;
+                    render(&game, &keymap, &mut screen);
                 }
-                if game.first_move {
-                    game.first_move = false;
+                continue;
+            }
+        }
+        // While the go to prompt is open, keys edit it instead of playing
+        if game.goto_input.is_some() {
+            if let Event::Key(key) = event {
+                edit_goto(&mut game, key);
+                render(&game, &keymap, &mut screen);
+            }
+            continue;
+        }
+        // Digits that aren't bound to anything build up a count for the next movement
+        match event {
+            Event::Key(Key::Char(digit @ '0'..='9'))
+                if game.state == GameState::Playing
+                    && keymap.action(Key::Char(digit)).is_none() =>
+            {
+                let digit = digit.to_digit(10).unwrap_or(0) as usize;
+                let count = game.pending_count.unwrap_or(0) * 10 + digit;
+                // A leading zero isn't a count
+                if count > 0 {
+                    game.pending_count = Some(count.min(MAX_COUNT));
+                    render(&game, &keymap, &mut screen);
                 }
+                continue;
             }
-            Key::Char('f') => {
-                let cell_index = cell_from_pos(
-                    game.board.selected_row as i8,
-                    game.board.selected_col as i8,
-                    &game.board,
-                )
-                .expect("Selected cell doesn't exist");
-                game.board.cells[cell_index].is_flagged = !game.board.cells[cell_index].is_flagged;
+            Event::Key(Key::Esc) if game.pending_count.is_some() => {
+                game.pending_count = None;
+                render(&game, &keymap, &mut screen);
+                continue;
             }
-            Key::Char('\n') => {}
             _ => {}
         }
-        render(&mut game);
+        // A hidden key in debug builds for checking that a panic leaves the terminal usable
+        if cfg!(debug_assertions) && event == Event::Key(Key::F(12)) {
+            panic!("F12 was pressed to test the panic hook");
+        }
+        let action = match event {
+            Event::Key(key) => keymap.action(key),
+            Event::Unsupported(bytes) => modified_key_action(&bytes),
+            Event::Mouse(MouseEvent::Press(button, x, y)) if game.state == GameState::Playing => {
+                mouse_action(&mut game, &screen, button, x, y)
+            }
+            _ => None,
+        };
+        let action = match action {
+            Some(action) => action,
+            None => continue,
+        };
+        let allowed = match game.state {
+            GameState::Playing => true,
+            // The board stays hidden until the game is resumed
+            GameState::Paused => matches!(action, Action::Quit | Action::Pause | Action::Redraw),
+            // Once the game is over the only things left to do are quitting, starting again or
+            // taking back the last move
+            GameState::Won | GameState::Lost => matches!(
+                action,
+                Action::Quit
+                    | Action::Menu
+                    | Action::PlayMacro
+                    | Action::Restart
+                    | Action::Retry
+                    | Action::Undo
+                    | Action::Redo
+                    | Action::Redraw
+                    | Action::Minimap
+                    | Action::Log
+                    | Action::Zoom
+                    | Action::Help
+            ),
+        };
+        if !allowed {
+            continue;
+        }
+        game.message = None;
+        game.notified = None;
+        // The preview only holds for the board as it was, so anything else clears it
+        if action != Action::ChordPreview {
+            game.board.preview = None;
+        }
+        // Any key uses up the count, but only movement makes use of it
+        let count = match (action, game.pending_count.take()) {
+            (_, Some(count)) => {
+                acceleration.reset();
+                count
+            }
+            (Action::Move(direction), None) => acceleration.steps(direction),
+            (_, None) => {
+                acceleration.reset();
+                1
+            }
+        };
+        let was_over = game.is_over();
+        match action {
+            Action::Quit if game.in_progress() => game.cover_board(Ui::ConfirmQuit),
+            Action::Quit => break,
+            Action::Menu => game.cover_board(Ui::Menu(0)),
+            // Reading the keys doesn't cost any time
+            Action::Help => game.cover_board(Ui::Help(0)),
+            Action::Restart => game.restart(),
+            Action::Pause => {
+                if !game.toggle_pause() {
+                    game.notify("This time attack can't be paused, but the settings can change that for the next one")
+                }
+            }
+            // Replaying the same board only makes sense once it's been finished
+            Action::Retry if game.state != GameState::Playing => game.retry(),
+            Action::Retry => {}
+            Action::Undo if game.out_of_time => {
+                game.notify("Running out of time can't be taken back")
+            }
+            Action::Undo => {
+                let undone = game.undo();
+                if !undone {
+                    game.notify("There is nothing to undo");
+                }
+            }
+            Action::Redo => {
+                let redone = game.redo();
+                if !redone {
+                    game.notify("There is nothing to redo");
+                }
+            }
+            Action::GoTo => game.goto_input = Some(String::new()),
+            Action::Redraw => screen.request_redraw(),
+            // It would show the whole board through the fog
+            Action::Minimap if game.rules.fog.is_some() => {
+                game.notify("There is no minimap in fog of war")
+            }
+            Action::Minimap => game.show_minimap = !game.show_minimap,
+            Action::Log => {
+                game.show_log = !game.show_log;
+                // The pane leaves nothing behind once it is gone only if everything is drawn again
+                screen.request_redraw();
+            }
+            Action::ChordPreview => {
+                let board = &mut game.board;
+                let index = board.selected_index();
+                let cell = &board.cells[index];
+                if board.preview.is_some() {
+                    board.preview = None;
+                } else if game.rules.no_flags {
+                    game.notify(NO_FLAGS);
+                } else if cell.is_revealed && !cell.is_mine() && cell.mines_nearby {
+                    board.preview = Some(index);
+                } else {
+                    game.notify("Only revealed numbers can be chorded");
+                }
+            }
+            Action::Zoom => {
+                let settings = &mut game.board.settings;
+                settings.spacing = match settings.spacing {
+                    Spacing::Compact => Spacing::Normal,
+                    _ => Spacing::Compact,
+                };
+            }
+            Action::Neighbourhood => {
+                let settings = &mut game.board.settings;
+                settings.neighbourhood = !settings.neighbourhood;
+            }
+            Action::Crosshair => {
+                let settings = &mut game.board.settings;
+                settings.crosshair = !settings.crosshair;
+            }
+            Action::EffectiveNumbers => {
+                let settings = &mut game.board.settings;
+                settings.effective_numbers = !settings.effective_numbers;
+            }
+            Action::Record => game.toggle_recording(),
+            Action::PlayMacro => {
+                play_macro(&mut game, &keymap, &mut screen, macro_delay, fast_step)
+            }
+            Action::Reveal if game.first_move => {
+                // Looking for a board without guessing can take a moment, so it is shown
+                let board = &game.board;
+                let index = board.selected_index();
+                if !board.cells[index].is_flagged() {
+                    game.place_mines(index, &mut |game| render(game, &keymap, &mut screen));
+                }
+                if play(&mut game, action, count, fast_step) {
+                    game.record(MacroStep::Action(action, count));
+                }
+            }
+            _ => {
+                // Steps that did nothing would only stop the macro when it is played
+                let done = play(&mut game, action, count, fast_step);
+                if done {
+                    game.record(MacroStep::Action(action, count));
+                }
+            }
+        }
+        // Show the result as soon as a move ends the game
+        if !was_over && game.is_over() {
+            game.ui = Ui::GameOver(0);
+            game.record_daily();
+        }
+        render(&game, &keymap, &mut screen);
+        held = spread_reveal(&mut game, &keymap, &mut screen, &inputs, reveal_delay);
     }
-
-    // Reshow the cursor
-    write!(stdout, "{}", termion::cursor::Show).unwrap();
+    terminal::restore();
 }
 
-pub fn calculate_adjacent_mines(board: &mut Board) {
-    for index in 0..board.cells.len() {
-        board.cells[index].adjacent_mines = adjacent_mines(&board, &board.cells[index]);
+/// Shows the cells the last move opened a ring at a time, `delay` apart. The move itself is
+/// already over, so anything that comes in first shows the rest at once and is handed back to
+/// be dealt with as usual.
+fn spread_reveal(
+    game: &mut Minesweeper,
+    keymap: &Keymap,
+    screen: &mut Screen,
+    inputs: &Receiver<Input>,
+    delay: Duration,
+) -> Option<Input> {
+    let mut wave = game.wave.take()?;
+    let mut held = None;
+    'rings: while wave.step(&mut game.board) {
+        let next = Instant::now() + delay;
+        // Ticks don't interrupt, and the timer can wait until the reveal is done
+        while let Some(left) = next.checked_duration_since(Instant::now()) {
+            match inputs.recv_timeout(left) {
+                Ok(Input::Tick) => {}
+                Ok(Input::Resize) => screen.request_redraw(),
+                Ok(input) => {
+                    held = Some(input);
+                    break 'rings;
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    held = Some(Input::Closed);
+                    break 'rings;
+                }
+            }
+        }
+        render(game, keymap, screen);
     }
+    wave.finish(&mut game.board);
+    render(game, keymap, screen);
+    held
 }
 
-pub fn reveal_cells_around(board: &mut Board, cell_index: usize) {
-    let cell = &board.cells[cell_index];
-    for cell_near_index_opt in cells_around(&board, &cell) {
-        match cell_near_index_opt {
-            Some(cell_near_index) => {
-                if board.cells[cell_near_index].is_mine == false
-                    && board.cells[cell_near_index].is_revealed == false
-                {
-                    board.cells[cell_near_index].is_revealed = true;
-                    if board.cells[cell_near_index].adjacent_mines == 0 {
-                        reveal_cells_around(board, cell_near_index);
-                    }
+/// Carries out a choice from the menu or the box shown when a game ends, returning `false` if it
+/// was to quit
+fn choose(
+    item: MenuItem,
+    game: &mut Minesweeper,
+    config: &Config,
+    keymap: &Keymap,
+    inputs: &Receiver<Input>,
+    screen: &mut Screen,
+) -> bool {
+    match item {
+        MenuItem::Resume => game.ui = Ui::Board,
+        MenuItem::Restart => game.restart(),
+        MenuItem::SameBoard => {
+            game.retry();
+            game.ui = Ui::Board;
+        }
+        MenuItem::NewBoard => {
+            let title = TitleScreen::new(game.setup());
+            match show_title(title, inputs, keymap, screen, true) {
+                TitleOutcome::Start(setup) => {
+                    let mut next = new_game(&setup, config);
+                    game.carry_settings(&mut next);
+                    *game = next;
                 }
+                TitleOutcome::Quit => return false,
+                // Backing out goes back to the game as it was
+                TitleOutcome::Continue | TitleOutcome::Cancel => {}
             }
-            None => {}
+            screen.request_redraw();
         }
+        MenuItem::Settings => game.ui = Ui::Settings(0),
+        MenuItem::Quit => return false,
     }
+    true
 }
 
-pub fn cells_around(board: &Board, cell: &Cell) -> Vec<Option<usize>> {
-    return vec![
-        relative_cell_index(-1, -1, cell, board),
-        relative_cell_index(-1, 0, cell, board),
-        relative_cell_index(-1, 1, cell, board),
-        relative_cell_index(0, -1, cell, board),
-        relative_cell_index(0, 1, cell, board),
-        relative_cell_index(1, -1, cell, board),
-        relative_cell_index(1, 0, cell, board),
-        relative_cell_index(1, 1, cell, board),
-    ];
+/// Carries out an action that moves the selection or plays a cell, repeating movement `count`
+/// times. Returns `false` if the action couldn't be done, like revealing a cell that is already
+/// revealed.
+fn play(game: &mut Minesweeper, action: Action, count: usize, fast_step: usize) -> bool {
+    let (row, col) = (game.board.selected_row, game.board.selected_col);
+    match action {
+        Action::Move(direction) | Action::MoveFast(direction) => {
+            let steps = match action {
+                Action::MoveFast(_) => count * fast_step,
+                _ => count,
+            };
+            if !game.board.move_selection_by(direction, steps) {
+                game.notify("That is the edge of the board");
+                return false;
+            }
+        }
+        // Like in vim, a count picks the row or column that far in from the edge
+        Action::MoveToEdge(direction) => {
+            game.board.move_selection_to_edge(direction);
+            game.board
+                .move_selection_by(direction.opposite(), count - 1);
+        }
+        Action::MoveToCorner(first, second) => {
+            game.board.move_selection_to_edge(first);
+            game.board.move_selection_to_edge(second);
+        }
+        Action::NextFlag | Action::PreviousFlag => {
+            match game.board.next_flag(action == Action::NextFlag) {
+                Some(index) => game.board.select(index),
+                None => {
+                    game.notify("There are no flags to go to");
+                    return false;
+                }
+            }
+        }
+        Action::NextLayer | Action::PreviousLayer => {
+            if game.board.depth == 1 {
+                game.notify("This board only has one layer");
+                return false;
+            }
+            if !game.board.change_layer(action == Action::NextLayer) {
+                game.notify("There are no more layers that way");
+                return false;
+            }
+        }
+        Action::NearestCovered => {
+            let found = game.select_nearest_covered();
+            if !found {
+                game.notify("There are no other covered cells");
+                return false;
+            }
+        }
+        Action::Reveal => {
+            let result = game.reveal(row, col);
+            if result == Some(RevealResult::Flagged) {
+                game.notify("That cell is flagged, unflag it to reveal");
+            }
+            return matches!(result, Some(RevealResult::Safe) | Some(RevealResult::Mine));
+        }
+        Action::Flag | Action::Question | Action::Chord | Action::ChordAll
+            if game.rules.no_flags =>
+        {
+            game.notify(NO_FLAGS);
+            return false;
+        }
+        Action::Chord => {
+            let chorded = game.chord(row, col);
+            if !chorded {
+                let cell = &game.board.cells[game.board.selected_index()];
+                game.notify(match cell.is_revealed && cell.mines_nearby {
+                    true => "The flags around it don't match the number",
+                    false => "Only revealed numbers can be chorded",
+                });
+            }
+            return chorded;
+        }
+        Action::Question => {
+            return game.toggle_question(row, col) == Some(FlagResult::Toggled);
+        }
+        Action::ChordAll => {
+            let opened = game.chord_all();
+            match opened {
+                0 => game.notify("There are no numbers that can be chorded"),
+                _ => {
+                    game.message
+                        .get_or_insert(format!("Chording opened {} cells", opened));
+                }
+            }
+            return opened > 0;
+        }
+        Action::Flag => {
+            let result = game.toggle_flag(row, col);
+            if result == Some(FlagResult::LimitReached) {
+                game.notify("Every flag has been placed already");
+            }
+            return result == Some(FlagResult::Toggled);
+        }
+        _ => return false,
+    }
+    true
 }
 
-pub fn adjacent_mines(board: &Board, cell: &Cell) -> i8 {
-    let mut count = 0;
-    for index in cells_around(&board, &cell) {
-        match index {
-            Some(i) => {
-                if board.cells[i].is_mine {
-                    count += 1;
-                }
+/// Plays the recorded macro from the start of the current board, pausing between steps so it
+/// can be watched. Playback stops as soon as a step can't be done, which happens once the game
+/// has gone differently from when the macro was recorded.
+fn play_macro(
+    game: &mut Minesweeper,
+    keymap: &Keymap,
+    screen: &mut Screen,
+    delay: Duration,
+    fast_step: usize,
+) {
+    if game.recording.is_some() || game.recorded.is_empty() {
+        game.notify("There is no macro to play");
+        return;
+    }
+    game.retry();
+    let steps = game.recorded.clone();
+    for (number, &step) in steps.iter().enumerate() {
+        // Steps come too quickly to watch each opening spread
+        game.finish_wave();
+        render(game, keymap, screen);
+        thread::sleep(delay);
+        let done = match step {
+            MacroStep::Action(action, count) => play(game, action, count, fast_step),
+            MacroStep::Select(row, col) => {
+                game.board.selected_row = row;
+                game.board.selected_col = col;
+                true
             }
-            None => {}
+        };
+        let finished = game.state != GameState::Playing && number + 1 < steps.len();
+        if !done || finished {
+            game.message = Some(format!(
+                "The macro stopped at step {} of {}",
+                number + 1,
+                steps.len()
+            ));
+            return;
         }
     }
+}
 
-    return count;
+/// Starts a game as chosen on the title screen, with the settings from the config file
+fn new_game(setup: &Setup, config: &Config) -> Minesweeper {
+    let mut board = setup
+        .board()
+        .expect("The title screen only starts boards that can be made");
+    board.settings = config.settings.clone();
+    let mut game = Minesweeper::new(board, setup.opening);
+    game.lives = setup.lives;
+    game.rules = Rules {
+        pause_countdown: config.settings.pause_countdown,
+        ..setup.rules
+    };
+    game.preset = setup.preset;
+    game
+}
+
+/// Shows the title screen until the player starts a game or quits. Backing out with escape only
+/// leaves when `can_cancel` is set, for when there is a game to go back to.
+fn show_title(
+    mut title: TitleScreen,
+    inputs: &Receiver<Input>,
+    keymap: &Keymap,
+    screen: &mut Screen,
+    can_cancel: bool,
+) -> TitleOutcome {
+    draw_title(&title, keymap, screen);
+    for input in inputs {
+        match input {
+            Input::Event(Event::Key(key)) => match title.handle(key, keymap) {
+                TitleOutcome::Continue => {}
+                TitleOutcome::Cancel if !can_cancel => {}
+                outcome => return outcome,
+            },
+            Input::Resize => {}
+            Input::Event(_) | Input::Tick => continue,
+            Input::Closed => break,
+        }
+        draw_title(&title, keymap, screen);
+    }
+    TitleOutcome::Quit
 }
 
-fn render(game: &Minesweeper) {
-    let mut screen = "".to_string();
-    screen += &format!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
-    screen += &format!("{}\n", game.board);
-    screen += &format!(
-        "r: {}, c: {}, enter: flag, space: safe",
-        game.board.selected_row, game.board.selected_col
+/// Draws the title screen in a box in the middle of the terminal
+fn draw_title(title: &TitleScreen, keymap: &Keymap, screen: &mut Screen) {
+    let lines = title.lines(keymap);
+    let (width, height) = box_size(&lines);
+    let (columns, rows) = termion::terminal_size().unwrap_or((0, 0));
+    let x = (columns as usize).saturating_sub(width) / 2 + 1;
+    let y = (rows as usize).saturating_sub(height) / 2 + 1;
+    screen.show(&format!(
+        "{}{}",
+        termion::clear::All,
+        draw_box(&lines, x as u16, y as u16)
+    ));
+}
+
+/// Draws the game on the terminal. Only what changed since the last frame is drawn, unless the
+/// screen has to be drawn in full.
+fn render(game: &Minesweeper, keymap: &Keymap, screen: &mut Screen) {
+    if game.bell.take() {
+        screen.show("\x07");
+    }
+    let (columns, lines) = minimum_size(&game.board, STATUS_LINES);
+    match termion::terminal_size() {
+        Ok((width, height)) if (width as usize) < columns || (height as usize) < lines => {
+            screen.show(&format!(
+                "{}{}Terminal too small: need {}x{}, have {}x{}",
+                termion::clear::All,
+                termion::cursor::Goto(1, 1),
+                columns,
+                lines,
+                width,
+                height
+            ));
+            screen.request_redraw();
+            return;
+        }
+        _ => {}
+    }
+    if game.state == GameState::Paused {
+        // Hide the board so it can't be studied while the game is paused
+        screen.show(&format!(
+            "{}{}{}",
+            termion::clear::All,
+            termion::cursor::Goto(1, 1),
+            status_bar(game, keymap, &Viewport::whole(&game.board), 1)
+        ));
+        screen.request_redraw();
+        return;
+    }
+    let menu = match game.ui {
+        Ui::Board | Ui::ConfirmQuit | Ui::GameOver(_) => None,
+        Ui::Help(page) => {
+            let pages = help_pages(keymap);
+            let mut lines = pages[page.min(pages.len() - 1)].clone();
+            lines.push(String::new());
+            lines.push(match pages.len() {
+                1 => "any key: close".to_string(),
+                count if page + 1 < count => {
+                    format!("page {} of {}, any key: next, esc: close", page + 1, count)
+                }
+                count => format!("page {} of {}, any key: close", page + 1, count),
+            });
+            Some(lines)
+        }
+        Ui::Menu(selected) => {
+            let labels: Vec<String> = MENU_ITEMS
+                .iter()
+                .map(|item| item.label().to_string())
+                .collect();
+            Some(menu_lines(&labels, selected))
+        }
+        Ui::Settings(selected) => {
+            let mut labels: Vec<String> = SETTINGS
+                .iter()
+                .map(|setting| {
+                    let label = format!("{}: {}", setting.label(), setting.value(&game.board));
+                    // Settings that need a new board can't be changed while a game is going
+                    if setting.needs_new_board() && game.in_progress() {
+                        let (start, end) = (termion::style::Faint, termion::style::NoFaint);
+                        game.board.style(start, &label, end)
+                    } else {
+                        label
+                    }
+                })
+                .collect();
+            labels.push("Save".to_string());
+            labels.push("Back".to_string());
+            Some(menu_lines(&labels, selected))
+        }
+    };
+    // The board makes room for the panes down the right and the gap beside them
+    // Fog of war hides the board beyond the cursor, which the minimap would give away, even when
+    // it was left open from the last game
+    let show_minimap = game.show_minimap && game.board.fog.is_none();
+    let pane_width = match (show_minimap, game.show_log) {
+        (false, false) => None,
+        (true, false) => Some(game.minimap.width()),
+        (false, true) => Some(LOG_COLUMNS),
+        (true, true) => Some(game.minimap.width().max(LOG_COLUMNS)),
+    };
+    let reserved = pane_width.map_or(0, |width| width + 3);
+    let view = screen.scroll(
+        &game.board,
+        STATUS_LINES,
+        reserved,
+        game.board.settings.centre,
     );
-    // Draw stdout from top left relative
-    println!("{}", screen);
+    let status = status_bar(game, keymap, &view, screen.origin().0);
+    let mut frame = screen.draw(&game.board, &status);
+    // The log goes under the minimap, taking up as much of the rest of the terminal as it needs
+    let mut top = 1;
+    if show_minimap {
+        let lines = game.minimap.lines(&game.board, &view);
+        frame += &screen.draw_pane(Pane::Minimap, top, &lines, game.minimap.width());
+        top += lines.len() as u16 + 2;
+    }
+    if game.show_log {
+        let rows = termion::terminal_size().map_or(0, |(_, rows)| rows);
+        let room = rows.saturating_sub(top + 1) as usize;
+        if room > 0 {
+            let lines = game.log.lines(room.min(LOG_LINES));
+            frame += &screen.draw_pane(Pane::Log, top, &lines, LOG_COLUMNS);
+        }
+    }
+    if let Some(menu) = menu {
+        // Drawn last so that it covers the board, which then has to be drawn again in full once
+        // the menu is gone
+        let (x, y) = screen.origin();
+        frame += &draw_box(&menu, x + 2, y + 1);
+        screen.request_redraw();
+    }
+    if let Ui::GameOver(selected) = game.ui {
+        let mut lines = game.summary().lines();
+        lines.push(String::new());
+        let labels: Vec<String> = GAME_OVER_ITEMS
+            .iter()
+            .map(|(_, label)| label.to_string())
+            .collect();
+        lines.extend(menu_lines(&labels, selected));
+        // Centred over the board, which stays visible around it
+        let (width, height) = box_size(&lines);
+        let (x, y) = screen.origin();
+        let x = x as usize + game.board.text_width(&view).saturating_sub(width) / 2;
+        let y = y as usize + game.board.text_height(&view).saturating_sub(height) / 2;
+        frame += &draw_box(&lines, x as u16, y as u16);
+        screen.request_redraw();
+    }
+    screen.show(&frame);
 }
 
-fn relative_cell_index(delta_row: i8, delta_col: i8, cell: &Cell, board: &Board) -> Option<usize> {
-    let row = cell.row as i8 + delta_row;
-    let col = cell.col as i8 + delta_col;
-    if row < 0 || row >= board.height as i8 || col < 0 || col >= board.width as i8 {
-        return None;
+/// The help screen's lines split into pages that fit on the terminal, leaving room for the
+/// frame and the line saying how to turn the page
+fn help_pages(keymap: &Keymap) -> Vec<Vec<String>> {
+    let rows = termion::terminal_size().map_or(usize::MAX, |(_, rows)| rows as usize);
+    let per_page = rows.saturating_sub(5).max(1);
+    keymap
+        .help_lines()
+        .chunks(per_page)
+        .map(<[String]>::to_vec)
+        .collect()
+}
+
+/// How many lines the status bar takes up under the board
+const STATUS_LINES: usize = 3;
+
+/// The lines under the board: the state of the game, the keys that can be pressed, and a line
+/// kept free for messages and questions. The numbers are padded to the most digits they can
+/// have on this board so the line doesn't shift about as they change. The lines start at column
+/// `x` of the terminal.
+fn status_bar(game: &Minesweeper, keymap: &Keymap, view: &Viewport, x: u16) -> String {
+    let board = &game.board;
+    let state = match game.state {
+        GameState::Playing => "Playing",
+        GameState::Paused => "Paused",
+        GameState::Won => "Won",
+        GameState::Lost => "Lost",
+    };
+    // Room for a minus sign, as there can be more flags than mines
+    let mines_width = board.mines.to_string().len() + 1;
+    let cells_width = board.cells.len().to_string().len();
+    let coordinates = board.settings.coordinates;
+    let row_width = coordinates.row(board.height - 1).len();
+    let col_width = coordinates.col(board.width - 1).len();
+    // A time attack counts down instead, in whole seconds rounded up so that 0:00 means it's over
+    let (clock, time) = match game.time_left() {
+        // The timer still runs underneath, it just isn't shown
+        None if game.rules.hide_timer => ("mode", "zen".to_string()),
+        Some(left) => {
+            let seconds = left.as_secs() + (left.subsec_nanos() > 0) as u64;
+            ("time left", format_duration(Duration::from_secs(seconds)))
+        }
+        None => ("time", format_duration(game.timer.elapsed())),
+    };
+    let head = format!("{:<7} | {}: ", state, clock);
+    let time = format!("{:>5}", time);
+    let mut status = format!(
+        "{}{} | mines left: {:>mines_width$} | flags: {:>cells_width$} | cells left: {:>cells_width$} | r: {:>row_width$}, c: {:>col_width$}",
+        head,
+        time,
+        board.mines_left(),
+        board.flags,
+        board.safe_cells_left,
+        coordinates.row(board.selected_row),
+        coordinates.col(board.selected_col),
+    );
+    if board.depth > 1 {
+        status += &format!(" | layer: {} of {}", board.layer + 1, board.depth);
+    }
+    if game.lives > 1 {
+        status += &format!(
+            " | {}{}",
+            "\u{2665}".repeat(game.lives_left()),
+            "\u{2661}".repeat(game.lives - game.lives_left())
+        );
     }
-    for (index, iter_cell) in board.cells.iter().enumerate() {
-        if iter_cell.row as i8 == row && iter_cell.col as i8 == col {
-            return Some(index);
+    if board.settings.flag_limit && game.state == GameState::Playing {
+        status += &format!(" (flags left: {})", board.mines_left());
+    }
+    if board.over_cap > 0 {
+        status += " (number cap not met)";
+    }
+    if game.rules.no_flags {
+        status += " (NF)";
+    }
+    if game.rules.fog.is_some() {
+        status += " (fog)";
+    }
+    if game.rules.moving_mines.is_some() {
+        status += " (moving mines)";
+    }
+    if board.settings.auto_chord {
+        status += " (auto-chord)";
+    }
+    if board.settings.auto_flag {
+        status += " (auto-flag)";
+    }
+    if game.retry {
+        status += " (retry)";
+    }
+    if game.recording.is_some() {
+        status += " (recording)";
+    }
+    if let Some(count) = game.pending_count {
+        status += &format!(" (count: {})", count);
+    }
+    status += &format!(" | seed: {}", board.seed);
+    if let Some(daily) = game.daily {
+        status += &format!(" | Daily {}", daily.date);
+        if daily.practice {
+            status += " (practice)";
         }
     }
-    None
+    // The bar gets whatever room is left on the line from column `x`, up to its full width
+    let columns = termion::terminal_size().map_or(usize::MAX, |(columns, _)| {
+        (columns + 1).saturating_sub(x) as usize
+    });
+    let cleared = format!(
+        " | cleared: {}/{} {:>3}%",
+        board.safe_cells - board.safe_cells_left,
+        board.safe_cells,
+        board.cleared_percentage()
+    );
+    let room = columns.saturating_sub(status.chars().count() + cleared.chars().count() + 3);
+    status += &cleared;
+    if room >= MIN_PROGRESS_BAR {
+        let bar = progress_bar(board.cleared_percentage(), room.min(PROGRESS_BAR));
+        status += &format!(" {}", bar);
+    }
+    // Without a border for the arrows to go in, the status bar shows where the rest of the board is
+    if board.settings.border == Border::None {
+        let more: String = [
+            (view.row > 0, '\u{2191}'),
+            (view.row + view.rows < board.height, '\u{2193}'),
+            (view.col > 0, '\u{2190}'),
+            (view.col + view.cols < board.width, '\u{2192}'),
+        ]
+        .iter()
+        .filter(|(more, _)| *more)
+        .map(|&(_, arrow)| arrow)
+        .collect();
+        if !more.is_empty() {
+            status += &format!(" (more: {})", more);
+        }
+    }
+    let hints = match game.state {
+        GameState::Playing => format!(
+            "{}: flag, {}: safe, {}: pause, move: {}",
+            keymap.describe(Action::Flag),
+            keymap.describe(Action::Reveal),
+            keymap.describe(Action::Pause),
+            [
+                Direction::Up,
+                Direction::Left,
+                Direction::Down,
+                Direction::Right
+            ]
+            .map(|direction| keymap.describe(Action::Move(direction)))
+            .join(" ")
+        ),
+        GameState::Paused => format!(
+            "{}: resume, {}: quit",
+            keymap.describe(Action::Pause),
+            keymap.describe(Action::Quit)
+        ),
+        GameState::Won if game.rules.hide_timer => {
+            format!("You win! {}", game_over_help(keymap))
+        }
+        GameState::Won => format!(
+            "You win in {}! {}",
+            format_duration(game.timer.elapsed()),
+            game_over_help(keymap)
+        ),
+        GameState::Lost if game.out_of_time => format!(
+            "Out of time after {}! {}",
+            format_duration(game.timer.elapsed()),
+            game_over_help(keymap)
+        ),
+        GameState::Lost => format!(
+            "You lost after {}! {}",
+            format_duration(game.timer.elapsed()),
+            game_over_help(keymap)
+        ),
+    };
+    let message = if game.ui == Ui::ConfirmQuit {
+        "Quit? (y/n)".to_string()
+    } else if let Some(input) = &game.goto_input {
+        format!("{}: {}", board.settings.coordinates.prompt(), input)
+    } else {
+        game.message.clone().unwrap_or_default()
+    };
+    let paint = |line: &str| board.paint(board.settings.theme.status, line);
+    // A notice stands out from the lines above until it's taken down
+    let message = match game.notified {
+        Some(_) if !message.is_empty() => format!(
+            "{}{}{}",
+            termion::style::Invert,
+            paint(&message),
+            termion::style::NoInvert
+        ),
+        _ => paint(&message),
+    };
+    // The countdown stands out once it is running low
+    let status = match game.time_left() {
+        Some(left) if left < COUNTDOWN_WARNING && game.state != GameState::Won => {
+            let (head, rest) = status.split_at(head.len());
+            let rest = &rest[time.len()..];
+            let warning = board.paint(board.settings.theme.wrong_flag, &time);
+            format!("{}{}{}", paint(head), warning, paint(rest))
+        }
+        _ => paint(&status),
+    };
+    format!("{}\r\n{}\r\n{}", status, paint(&hints), message)
 }
 
-fn cell_from_pos(row: i8, col: i8, board: &Board) -> Option<usize> {
-    for (index, iter_cell) in board.cells.iter().enumerate() {
-        if iter_cell.row as i8 == row && iter_cell.col as i8 == col {
-            return Some(index);
+/// How many cells Shift or Ctrl with an arrow key moves the selection by, unless the config file
+/// says otherwise
+const FAST_MOVE_STEP: usize = 5;
+
+/// How many quick presses of a movement key it takes before it speeds up, unless the config file
+/// says otherwise
+const ACCELERATE_AFTER: usize = 3;
+
+/// How soon a movement key has to be pressed again to count towards speeding up, unless the
+/// config file says otherwise
+const ACCELERATE_WINDOW_MS: u64 = 150;
+
+/// The largest count that can be typed before a movement key
+const MAX_COUNT: usize = 9999;
+
+/// Selects the cell that was clicked on and returns what the click should do to it. Clicks that
+/// miss the board do nothing.
+fn mouse_action(
+    game: &mut Minesweeper,
+    screen: &Screen,
+    button: MouseButton,
+    x: u16,
+    y: u16,
+) -> Option<Action> {
+    let action = match button {
+        MouseButton::Left => Action::Reveal,
+        MouseButton::Right => Action::Flag,
+        _ => return None,
+    };
+    let (left, top) = screen.origin();
+    let x = x.checked_sub(left)?;
+    let y = y.checked_sub(top)?;
+    let view = screen
+        .view()
+        .unwrap_or_else(|| Viewport::whole(&game.board));
+    let (row, col) = game.board.position_at(x as usize, y as usize, &view)?;
+    game.board.selected_row = row;
+    game.board.selected_col = col;
+    game.record(MacroStep::Select(row, col));
+    Some(action)
+}
+
+/// Handles a key typed into the go to prompt. Enter moves the selection to the typed position and
+/// escape closes the prompt without moving.
+fn edit_goto(game: &mut Minesweeper, key: Key) {
+    let coordinates = game.board.settings.coordinates;
+    let input = match game.goto_input.as_mut() {
+        Some(input) => input,
+        None => return,
+    };
+    match key {
+        Key::Char('\n') => {
+            let input = game.goto_input.take().unwrap_or_default();
+            match coordinates.parse(&input) {
+                Some((row, col)) if cell_from_pos(row, col, &game.board).is_some() => {
+                    game.board.selected_row = row;
+                    game.board.selected_col = col;
+                    game.record(MacroStep::Select(row, col));
+                }
+                _ => game.notify(&format!("There is no cell at \"{}\"", input)),
+            }
+        }
+        Key::Esc => game.goto_input = None,
+        Key::Backspace => {
+            input.pop();
         }
+        Key::Char(c) if coordinates.accepts(c) => input.push(c),
+        _ => {}
+    }
+}
+
+/// The keys that can still be used once a game is over
+fn game_over_help(keymap: &Keymap) -> String {
+    format!(
+        "{}: restart, {}: same board again, {}: quit",
+        keymap.describe(Action::Restart),
+        keymap.describe(Action::Retry),
+        keymap.describe(Action::Quit)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::tests::Output;
+
+    /// A game on a beginner board whose mines come from `seed`, placed straight away
+    fn game(seed: u64) -> Minesweeper {
+        let mut board = Board::new(9, 9, 10).unwrap();
+        board.seed(seed);
+        board.settings.reduce_motion = true;
+        Minesweeper::new(board, Opening::default())
+    }
+
+    /// Where the first cell that `wanted` picks is, as its row and column
+    fn find(game: &Minesweeper, wanted: impl Fn(&Cell) -> bool) -> (usize, usize) {
+        let cell = game.board.cells.iter().find(|cell| wanted(cell)).unwrap();
+        (cell.row, cell.col)
+    }
+
+    fn cell(game: &Minesweeper, (row, col): (usize, usize)) -> &Cell {
+        &game.board.cells[row * game.board.width + col]
+    }
+
+    #[test]
+    fn undo_and_redo_take_back_reveals_and_flags() {
+        let mut game = game(7);
+        let number = find(&game, |cell| !cell.is_mine() && cell.mines_nearby);
+        let mine = find(&game, |cell| cell.is_mine());
+        assert_eq!(game.reveal(number.0, number.1), Some(RevealResult::Safe));
+        assert!(cell(&game, number).is_revealed);
+        assert!(game.undo());
+        assert!(!cell(&game, number).is_revealed);
+        assert_eq!(game.state, GameState::Playing);
+
+        assert_eq!(game.toggle_flag(mine.0, mine.1), Some(FlagResult::Toggled));
+        assert_eq!(cell(&game, mine).mark, Mark::Flag(1));
+        assert_eq!(game.board.flags, 1);
+        assert!(game.undo());
+        assert_eq!(cell(&game, mine).mark, Mark::None);
+        assert_eq!(game.board.flags, 0);
+        assert_eq!(game.board.correct_flags, 0);
+
+        // The flag replaced the undone reveal, so only the flag comes back
+        assert!(game.redo());
+        assert_eq!(cell(&game, mine).mark, Mark::Flag(1));
+        assert_eq!(game.board.correct_flags, 1);
+        assert!(!game.redo());
+        assert!(!cell(&game, number).is_revealed);
+    }
+
+    #[test]
+    fn undoing_twice_and_redoing_twice_gets_back_to_the_same_board() {
+        let mut game = game(11);
+        let number = find(&game, |cell| !cell.is_mine() && cell.mines_nearby);
+        let mine = find(&game, |cell| cell.is_mine());
+        let safe_cells_left = game.board.safe_cells_left;
+        game.reveal(number.0, number.1);
+        game.toggle_flag(mine.0, mine.1);
+        assert!(game.undo());
+        assert!(game.undo());
+        assert!(!game.undo());
+        assert!(!cell(&game, number).is_revealed);
+        assert_eq!(cell(&game, mine).mark, Mark::None);
+        assert_eq!(game.board.safe_cells_left, safe_cells_left);
+
+        assert!(game.redo());
+        assert!(cell(&game, number).is_revealed);
+        assert_eq!(cell(&game, mine).mark, Mark::None);
+        assert!(game.redo());
+        assert_eq!(cell(&game, mine).mark, Mark::Flag(1));
+        assert_eq!(game.board.safe_cells_left, safe_cells_left - 1);
+    }
+
+    #[test]
+    fn forgiven_mines_are_taken_back_without_a_move_to_undo() {
+        let mut game = game(3);
+        game.rules = game.rules.zen();
+        let number = find(&game, |cell| !cell.is_mine() && cell.mines_nearby);
+        game.reveal(number.0, number.1);
+        // Found after the first reveal, which can move a mine out of the way
+        let mine = find(&game, |cell| cell.is_mine());
+        assert_eq!(game.reveal(mine.0, mine.1), Some(RevealResult::Mine));
+        assert!(!cell(&game, mine).is_revealed);
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.board.revealed_mines, 0);
+
+        // Only the first reveal is left to undo
+        assert!(game.undo());
+        assert!(!cell(&game, number).is_revealed);
+        assert!(!game.undo());
+        assert!(game.redo());
+        assert!(cell(&game, number).is_revealed);
+        assert!(!game.redo());
+    }
+
+    #[test]
+    fn undoing_a_reveal_puts_back_the_mine_it_moved() {
+        let layout = |game: &Minesweeper| -> Vec<(u8, i16)> {
+            let cells = game.board.cells.iter();
+            cells
+                .map(|cell| (cell.mines, cell.adjacent_mines))
+                .collect()
+        };
+        let mut game = game(5);
+        game.rules.moving_mines = Some(1);
+        let first = find(&game, |cell| !cell.is_mine() && cell.mines_nearby);
+        game.reveal(first.0, first.1);
+        let before = layout(&game);
+        let unrevealed = |cell: &Cell| !cell.is_mine() && cell.mines_nearby && !cell.is_revealed;
+        let second = find(&game, unrevealed);
+        assert_eq!(game.reveal(second.0, second.1), Some(RevealResult::Safe));
+        let shifted = layout(&game);
+        assert_ne!(shifted, before);
+
+        assert!(game.undo());
+        assert_eq!(layout(&game), before);
+        assert!(!cell(&game, second).is_revealed);
+        assert!(game.redo());
+        assert_eq!(layout(&game), shifted);
+        assert!(cell(&game, second).is_revealed);
+    }
+
+    #[test]
+    fn retrying_keeps_the_mines_and_nothing_else() {
+        let mut game = game(9);
+        game.rules.moving_mines = Some(1);
+        let number = find(&game, |cell| !cell.is_mine() && cell.mines_nearby);
+        game.reveal(number.0, number.1);
+        let mine = find(&game, |cell| cell.is_mine());
+        game.reveal(mine.0, mine.1);
+        assert_eq!(game.state, GameState::Lost);
+        let mines: Vec<u8> = game.board.cells.iter().map(|cell| cell.mines).collect();
+        game.nearest_search = Some((number, number));
+
+        game.retry();
+        assert!(game.retry);
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.rules.moving_mines, Some(1));
+        let retried: Vec<u8> = game.board.cells.iter().map(|cell| cell.mines).collect();
+        assert_eq!(retried, mines);
+        assert!(game.board.cells.iter().all(|cell| !cell.is_revealed));
+        assert_eq!(game.message, None);
+        assert!(game.log.lines(1)[0].trim().is_empty());
+        assert!(game.wave.is_none());
+        assert_eq!(game.nearest_search, None);
+        assert_eq!(game.board.detonated, None);
+        assert!(!game.undo());
+    }
+
+    #[test]
+    fn no_guess_boards_the_solver_cant_check_are_placed_once() {
+        let mut board = Board::new_multimine(9, 9, 20, 2).unwrap();
+        board.seed(1);
+        let opening = Opening {
+            no_guess: true,
+            ..Opening::default()
+        };
+        let mut game = Minesweeper::new(board, opening);
+        let mut progress = 0;
+        game.place_mines(40, &mut |_| progress += 1);
+        assert_eq!(progress, 0);
+        assert!(game.message.unwrap().contains("can't be checked"));
+        let mines: usize = game
+            .board
+            .cells
+            .iter()
+            .map(|cell| cell.mines as usize)
+            .sum();
+        assert_eq!(mines, 20);
+    }
+
+    #[test]
+    fn time_attacks_keep_to_the_pausing_they_started_with() {
+        let mut game = game(7);
+        game.rules.time_limit = Some(60);
+        game.rules.pause_countdown = false;
+        let number = find(&game, |cell| !cell.is_mine() && cell.mines_nearby);
+        game.reveal(number.0, number.1);
+        assert!(game.timer.is_running());
+        // Letting time attacks pause now is for the next one
+        game.board.settings.pause_countdown = true;
+        assert!(!game.toggle_pause());
+        assert_eq!(game.state, GameState::Playing);
+        assert!(game.timer.is_running());
+        game.cover_board(Ui::Menu(0));
+        assert!(game.timer.is_running());
+
+        game.restart();
+        game.timer.start();
+        assert!(game.toggle_pause());
+        assert_eq!(game.state, GameState::Paused);
+        assert!(!game.timer.is_running());
+    }
+
+    #[test]
+    fn games_are_drawn_through_the_screen() {
+        let output = Output::default();
+        let mut screen = Screen::new(Box::new(output.clone()));
+        let mut game = game(7);
+        game.board.settings.colours = false;
+        let keymap = Keymap::default();
+        render(&game, &keymap, &mut screen);
+        let frame = output.take();
+        assert!(frame.starts_with("\x1b[2J"));
+        assert!(frame.contains("[#]# # # # # # # # "));
+        assert!(frame.contains("Playing"));
+
+        game.board.settings.bell = true;
+        assert!(!game.undo());
+        game.notify("There is nothing to undo");
+        render(&game, &keymap, &mut screen);
+        let frame = output.take();
+        // The bell goes out before the frame that shows why it rang
+        assert!(frame.starts_with('\x07'));
+        assert!(frame.contains("There is nothing to undo"));
+        render(&game, &keymap, &mut screen);
+        assert!(!output.take().contains('\x07'));
+    }
+
+    #[test]
+    fn the_title_screen_is_drawn_through_the_screen() {
+        let output = Output::default();
+        let mut screen = Screen::new(Box::new(output.clone()));
+        let title = TitleScreen::new(Setup::default());
+        draw_title(&title, &Keymap::default(), &mut screen);
+        let frame = output.take();
+        assert!(frame.starts_with("\x1b[2J"));
+        assert!(frame.contains("Beginner"));
     }
-    None
 }
@@ -0,0 +1,143 @@
+use crate::board::Direction;
+use crate::keys::*;
+use termion::event::Key;
+
+/// What the player is looking at, which decides what key presses do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ui {
+    Board,
+    /// The menu opened with escape, along with the selected item
+    Menu(usize),
+    /// The settings opened from the menu, along with the selected setting
+    Settings(usize),
+    /// Asking whether to really quit a game in progress
+    ConfirmQuit,
+    /// The result of a game that just ended, along with the selected choice of what to do next
+    GameOver(usize),
+    /// The keys for every action, along with the page showing
+    Help(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Resume,
+    Restart,
+    /// Plays the same mine layout again
+    SameBoard,
+    NewBoard,
+    Settings,
+    Quit,
+}
+
+pub const MENU_ITEMS: [MenuItem; 5] = [
+    MenuItem::Resume,
+    MenuItem::Restart,
+    MenuItem::NewBoard,
+    MenuItem::Settings,
+    MenuItem::Quit,
+];
+
+/// The choices in the box shown when a game ends, with the labels they have there
+pub const GAME_OVER_ITEMS: [(MenuItem, &str); 4] = [
+    (MenuItem::Restart, "Play again"),
+    (MenuItem::SameBoard, "Same board"),
+    (MenuItem::NewBoard, "Change settings"),
+    (MenuItem::Quit, "Quit"),
+];
+
+impl MenuItem {
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuItem::Resume => "Resume",
+            MenuItem::Restart => "Restart",
+            MenuItem::SameBoard => "Same board",
+            MenuItem::NewBoard => "New board",
+            MenuItem::Settings => "Settings",
+            MenuItem::Quit => "Quit",
+        }
+    }
+}
+
+/// What a key press does in a menu
+pub enum MenuInput {
+    /// Moves the selection to another item
+    Select(usize),
+    /// Picks the selected item
+    Choose,
+    /// Leaves the menu
+    Close,
+    None,
+}
+
+/// Works out what a key does in a menu with `count` items. Movement keys move the selection,
+/// wrapping around at either end, enter picks an item and escape leaves.
+pub fn menu_input(key: Key, keymap: &Keymap, selected: usize, count: usize) -> MenuInput {
+    match key {
+        Key::Char('\n') => return MenuInput::Choose,
+        Key::Esc => return MenuInput::Close,
+        _ => {}
+    }
+    match keymap.action(key) {
+        Some(Action::Move(Direction::Up)) => MenuInput::Select((selected + count - 1) % count),
+        Some(Action::Move(Direction::Down)) => MenuInput::Select((selected + 1) % count),
+        Some(Action::Reveal) => MenuInput::Choose,
+        _ => MenuInput::None,
+    }
+}
+
+/// The lines of a menu, with a marker in front of the selected one
+pub fn menu_lines(labels: &[String], selected: usize) -> Vec<String> {
+    labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            let marker = if index == selected { '>' } else { ' ' };
+            format!("{} {}", marker, label)
+        })
+        .collect()
+}
+
+/// How many columns and lines `draw_box` takes up for the given lines
+pub fn box_size(lines: &[String]) -> (usize, usize) {
+    let width = lines
+        .iter()
+        .map(|line| visible_width(line))
+        .max()
+        .unwrap_or(0);
+    (width + 4, lines.len() + 2)
+}
+
+/// Draws the lines inside a box with its top left corner at the given position, on top of
+/// whatever is already on the screen
+pub fn draw_box(lines: &[String], x: u16, y: u16) -> String {
+    let width = lines
+        .iter()
+        .map(|line| visible_width(line))
+        .max()
+        .unwrap_or(0);
+    let mut rows = vec![format!("\u{250c}{}\u{2510}", "\u{2500}".repeat(width + 2))];
+    for line in lines {
+        let padding = " ".repeat(width - visible_width(line));
+        rows.push(format!("\u{2502} {}{} \u{2502}", line, padding));
+    }
+    rows.push(format!("\u{2514}{}\u{2518}", "\u{2500}".repeat(width + 2)));
+    rows.iter()
+        .enumerate()
+        .map(|(offset, row)| format!("{}{}", termion::cursor::Goto(x, y + offset as u16), row))
+        .collect()
+}
+
+/// How many columns a line takes up on the terminal, not counting the escape codes that style it
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Codes run from the escape up to and including a letter
+            chars.find(char::is_ascii_alphabetic);
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
@@ -0,0 +1,145 @@
+use crate::board::{Board, Viewport};
+
+/// The most columns and lines the minimap takes up, not counting its frame
+pub const MINIMAP_COLUMNS: usize = 24;
+pub const MINIMAP_LINES: usize = 8;
+
+/// How a single cell counts towards its block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Covered,
+    Revealed,
+    Flagged,
+}
+
+impl Kind {
+    fn of(board: &Board, index: usize) -> Kind {
+        let cell = &board.cells[index];
+        if cell.is_flagged() {
+            Kind::Flagged
+        } else if cell.is_revealed {
+            Kind::Revealed
+        } else {
+            Kind::Covered
+        }
+    }
+}
+
+/// How many cells of each kind a block holds
+#[derive(Debug, Clone, Copy, Default)]
+struct Block {
+    cells: usize,
+    revealed: usize,
+    flagged: usize,
+}
+
+impl Block {
+    fn count(&mut self, kind: Kind, by: isize) {
+        let counter = match kind {
+            Kind::Covered => return,
+            Kind::Revealed => &mut self.revealed,
+            Kind::Flagged => &mut self.flagged,
+        };
+        *counter = counter.wrapping_add_signed(by);
+    }
+}
+
+/// A small overview of a board too large to see at once, where each character stands for a
+/// block of cells. The blocks keep counts of their cells that are updated as cells change, so
+/// drawing it doesn't have to look at the whole board.
+pub struct Minimap {
+    /// How many rows and columns of cells each block covers
+    block_rows: usize,
+    block_cols: usize,
+    /// How many blocks there are across the minimap
+    width: usize,
+    blocks: Vec<Block>,
+    /// What each cell of the board was the last time it was counted
+    kinds: Vec<Kind>,
+}
+
+impl Minimap {
+    pub fn new(board: &Board) -> Minimap {
+        let block_rows = board.height.div_ceil(MINIMAP_LINES);
+        let block_cols = board.width.div_ceil(MINIMAP_COLUMNS);
+        let width = board.width.div_ceil(block_cols);
+        let height = board.height.div_ceil(block_rows);
+        let mut minimap = Minimap {
+            block_rows,
+            block_cols,
+            width,
+            blocks: vec![Block::default(); width * height],
+            kinds: vec![Kind::Covered; board.cells.len()],
+        };
+        for index in 0..board.cells.len() {
+            let block = minimap.block_of(board, index);
+            minimap.blocks[block].cells += 1;
+            minimap.update(board, index);
+        }
+        minimap
+    }
+
+    fn block_of(&self, board: &Board, index: usize) -> usize {
+        // Every layer of a layered board is counted in the same blocks
+        let (row, col) = (board.cells[index].row, board.cells[index].col);
+        (row / self.block_rows) * self.width + col / self.block_cols
+    }
+
+    /// Counts the cell at `index` again after it has changed
+    pub fn update(&mut self, board: &Board, index: usize) {
+        let kind = Kind::of(board, index);
+        let before = self.kinds[index];
+        if kind == before {
+            return;
+        }
+        let block = self.block_of(board, index);
+        self.blocks[block].count(before, -1);
+        self.blocks[block].count(kind, 1);
+        self.kinds[index] = kind;
+    }
+
+    /// The lines of the minimap, with the blocks inside `view` drawn inverted. Blocks with flags
+    /// show an `F`, fully revealed ones are blank, partly revealed ones are shaded and untouched
+    /// ones are solid.
+    pub fn lines(&self, board: &Board, view: &Viewport) -> Vec<String> {
+        let in_view = |block: usize| {
+            let (row, col) = (block / self.width, block % self.width);
+            let rows = row * self.block_rows..(row + 1) * self.block_rows;
+            let cols = col * self.block_cols..(col + 1) * self.block_cols;
+            rows.start < view.row + view.rows
+                && view.row < rows.end
+                && cols.start < view.col + view.cols
+                && view.col < cols.end
+        };
+        self.blocks
+            .chunks(self.width)
+            .enumerate()
+            .map(|(row, blocks)| {
+                let mut line = String::new();
+                for (col, block) in blocks.iter().enumerate() {
+                    let glyph = if block.flagged > 0 {
+                        board.paint(board.settings.theme.flag, "F")
+                    } else if block.revealed == block.cells {
+                        " ".to_string()
+                    } else if block.revealed > 0 {
+                        "\u{2592}".to_string()
+                    } else {
+                        "\u{2588}".to_string()
+                    };
+                    if in_view(row * self.width + col) {
+                        let (start, end) = (termion::style::Invert, termion::style::NoInvert);
+                        line += &board.style(start, &glyph, end);
+                    } else {
+                        line += &glyph;
+                    }
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// How many columns the minimap takes up
+    pub fn width(&self) -> usize {
+        self.width
+    }
+}
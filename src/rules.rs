@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+/// How a game is played and what counts, chosen along with the board and kept for the whole
+/// game so that results played by different rules are never mixed up. Each mode is a set of
+/// these, applied on top of the same reveals, undo and game states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    /// Flags and chording are off, the way speedrunners play "NF"
+    pub no_flags: bool,
+    /// How many seconds the board has to be cleared in, for a time attack
+    pub time_limit: Option<u64>,
+    /// Whether the timer is left off the status bar and the summary
+    pub hide_timer: bool,
+    /// Whether every move can be undone, rather than only the most recent ones
+    pub unlimited_undo: bool,
+    /// Whether revealing a mine takes back the move that found it instead of costing a life
+    pub forgive_mines: bool,
+    /// Whether the result is kept, like the daily board's
+    pub keep_results: bool,
+    /// How many cells from the selected one the board can be seen, for fog of war
+    pub fog: Option<usize>,
+    /// How many reveals go by between one of the mines moving somewhere else
+    pub moving_mines: Option<usize>,
+    /// Whether pausing a time attack stops its countdown. It is taken from the settings when the
+    /// game starts, so that it can't be turned on partway through a countdown.
+    pub pause_countdown: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            no_flags: false,
+            time_limit: None,
+            hide_timer: false,
+            unlimited_undo: false,
+            forgive_mines: false,
+            keep_results: true,
+            fog: None,
+            moving_mines: None,
+            pause_countdown: true,
+        }
+    }
+}
+
+impl Rules {
+    /// These rules in zen mode, where nothing is at stake. A countdown would be a worry, so
+    /// there isn't one.
+    pub fn zen(self) -> Rules {
+        Rules {
+            no_flags: self.no_flags,
+            time_limit: None,
+            hide_timer: true,
+            unlimited_undo: true,
+            forgive_mines: true,
+            keep_results: false,
+            fog: self.fog,
+            moving_mines: self.moving_mines,
+            pause_countdown: self.pause_countdown,
+        }
+    }
+
+    /// These rules without zen mode
+    pub fn unzen(self) -> Rules {
+        Rules {
+            no_flags: self.no_flags,
+            time_limit: self.time_limit,
+            fog: self.fog,
+            moving_mines: self.moving_mines,
+            pause_countdown: self.pause_countdown,
+            ..Rules::default()
+        }
+    }
+
+    pub fn is_zen(&self) -> bool {
+        *self == self.zen()
+    }
+
+    /// The time limit as a length of time
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit.map(Duration::from_secs)
+    }
+}
@@ -0,0 +1,349 @@
+use crate::board::{Board, Border, Spacing, Viewport};
+use crate::coords::Notation;
+use std::io::Write;
+use termion::cursor::Goto;
+
+/// How many frames can be drawn as changes only before the whole screen is drawn again, in case
+/// what is on the terminal has drifted from what was drawn
+const FULL_REDRAW_FRAMES: usize = 200;
+
+/// Where the top left of the board is drawn when it isn't centred. The terminal starts counting
+/// at 1.
+const TOP_LEFT: (u16, u16) = (1, 1);
+
+/// The smallest terminal, in columns and lines, that the board can be drawn on with its labels
+/// and border and `lines` lines under it. A board that scrolls only needs room for a single cell,
+/// but otherwise all of it has to fit.
+pub fn minimum_size(board: &Board, lines: usize) -> (usize, usize) {
+    if !board.settings.scroll {
+        let view = Viewport::whole(board);
+        return (board.text_width(&view), board.text_height(&view) + lines);
+    }
+    let (left, top) = board.grid_offset();
+    let thickness = board.settings.border.thickness();
+    let columns = left + board.cell_slot() + board.row_end() + thickness;
+    (columns, top + 1 + thickness + lines)
+}
+
+/// The boxes that can be drawn down the right of the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Minimap,
+    Log,
+}
+
+/// Everything that decides where things go on the screen. When any of it changes, the whole
+/// screen has to be drawn again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    width: usize,
+    height: usize,
+    cell_width: usize,
+    border: Border,
+    spacing: Spacing,
+    labels: bool,
+    coordinates: Notation,
+    view: Viewport,
+    /// The layer of the board being shown
+    layer: usize,
+    origin: (u16, u16),
+}
+
+impl Layout {
+    fn of(board: &Board, view: Viewport, origin: (u16, u16)) -> Layout {
+        Layout {
+            width: board.width,
+            height: board.height,
+            cell_width: board.cell_width(),
+            border: board.settings.border,
+            spacing: board.settings.spacing,
+            labels: board.settings.labels,
+            coordinates: board.settings.coordinates,
+            view,
+            layer: board.layer,
+            origin,
+        }
+    }
+}
+
+/// Remembers what was last drawn on the terminal, so that each frame only has to draw the cells
+/// and lines that changed rather than clearing the screen and drawing all of it again
+pub struct Screen {
+    /// Where frames are written, which is the terminal in raw mode while the game is played
+    out: Box<dyn Write>,
+    /// How each cell of the board was last drawn
+    cells: Vec<String>,
+    /// The lines last drawn under the board
+    lines: Vec<String>,
+    /// The lines last drawn in each pane, in the order of `Pane`
+    panes: [Vec<String>; 2],
+    layout: Option<Layout>,
+    /// The part of the board that fits on the terminal
+    view: Option<Viewport>,
+    /// Where the top left of the board is drawn
+    origin: (u16, u16),
+    /// How many frames have been drawn since the last full one
+    frames: usize,
+    /// Whether the next frame has to be drawn in full
+    redraw: bool,
+}
+
+impl Screen {
+    pub fn new(out: Box<dyn Write>) -> Screen {
+        Screen {
+            out,
+            cells: Vec::new(),
+            lines: Vec::new(),
+            panes: [Vec::new(), Vec::new()],
+            layout: None,
+            view: None,
+            origin: TOP_LEFT,
+            frames: 0,
+            redraw: true,
+        }
+    }
+
+    /// The part of the board that was last drawn
+    pub fn view(&self) -> Option<Viewport> {
+        self.view
+    }
+
+    /// Where the top left of the board was last drawn, which is what clicks are measured from
+    pub fn origin(&self) -> (u16, u16) {
+        self.origin
+    }
+
+    /// Writes a whole frame out at once. Nothing is flushed part way through, so the terminal
+    /// never shows half of one.
+    pub fn show(&mut self, frame: &str) {
+        self.out.write_all(frame.as_bytes()).unwrap();
+        self.out.flush().unwrap();
+    }
+
+    /// Makes the next frame clear the screen and draw everything, for when something else has
+    /// drawn over the board
+    pub fn request_redraw(&mut self) {
+        self.redraw = true;
+    }
+
+    /// Fits the view of the board to the terminal, keeping `lines` lines free under it, and
+    /// scrolls it to the selected cell. With `centre` on, the board and those lines are moved to
+    /// the middle of the terminal. The whole board is shown in the top left when the terminal's
+    /// size can't be found out.
+    pub fn scroll(
+        &mut self,
+        board: &Board,
+        lines: usize,
+        reserved_columns: usize,
+        centre: bool,
+    ) -> Viewport {
+        let mut view = match self.view {
+            // A different board starts off scrolled to the top left
+            Some(view) if view.rows <= board.height && view.cols <= board.width => view,
+            _ => Viewport::whole(board),
+        };
+        self.origin = TOP_LEFT;
+        if let Ok((columns, rows)) = termion::terminal_size() {
+            let columns = columns.saturating_sub(reserved_columns as u16);
+            // Without scrolling the whole board is drawn, as there has to be room for it
+            if board.settings.scroll {
+                view.follow(
+                    board,
+                    columns as usize,
+                    (rows as usize).saturating_sub(lines),
+                );
+            } else {
+                view = Viewport::whole(board);
+            }
+            if centre {
+                let width = board.text_width(&view);
+                let height = board.text_height(&view) + lines;
+                let spare = |size: u16, used: usize| (size as usize).saturating_sub(used) / 2;
+                self.origin = (
+                    TOP_LEFT.0 + spare(columns, width) as u16,
+                    TOP_LEFT.1 + spare(rows, height) as u16,
+                );
+            }
+        }
+        self.view = Some(view);
+        view
+    }
+
+    /// Returns what has to be written to the terminal to show the board with `lines` under it, as
+    /// it was last scrolled
+    pub fn draw(&mut self, board: &Board, lines: &str) -> String {
+        let view = self.view.unwrap_or_else(|| Viewport::whole(board));
+        let origin = self.origin;
+        let lines: Vec<String> = lines.split("\r\n").map(str::to_string).collect();
+        let cells: Vec<String> = (0..board.cells.len())
+            .map(|index| board.cell_text(index, &view))
+            .collect();
+        let layout = Layout::of(board, view, origin);
+        let full = self.redraw || self.layout != Some(layout) || self.frames >= FULL_REDRAW_FRAMES;
+        let mut frame = String::new();
+        let goto = |x: usize, y: usize| Goto(origin.0 + x as u16, origin.1 + y as u16);
+        if full {
+            frame += termion::clear::All.as_ref();
+            // Every line is moved to separately, as a carriage return would go back to the
+            // terminal's left edge rather than the board's
+            let text = board.draw(&view);
+            let text_lines = text.split("\r\n").chain(lines.iter().map(String::as_str));
+            for (number, line) in text_lines.enumerate() {
+                frame += &format!("{}{}", goto(0, number), line);
+            }
+            self.frames = 0;
+            self.redraw = false;
+        } else {
+            for (index, text) in cells.iter().enumerate() {
+                if self.cells[index] == *text {
+                    continue;
+                }
+                if let Some((x, y)) = board.cell_origin(index, &view) {
+                    frame += &format!("{}{}", goto(x, y), text);
+                }
+            }
+            let top = board.text_height(&view);
+            for number in 0..lines.len().max(self.lines.len()) {
+                let line = lines.get(number).map_or("", String::as_str);
+                if self.lines.get(number).map(String::as_str) != Some(line) {
+                    frame += &format!(
+                        "{}{}{}",
+                        goto(0, top + number),
+                        line,
+                        termion::clear::UntilNewline
+                    );
+                }
+            }
+            self.frames += 1;
+        }
+        self.cells = cells;
+        self.lines = lines;
+        self.layout = Some(layout);
+        frame
+    }
+
+    /// Returns what has to be written to draw `lines`, each `width` columns wide, in a frame
+    /// against the right edge of the terminal with its top on line `top`. Like the board, only
+    /// the lines that changed are drawn unless the last frame was drawn in full.
+    pub fn draw_pane(&mut self, pane: Pane, top: u16, lines: &[String], width: usize) -> String {
+        let columns = termion::terminal_size().map_or(0, |(columns, _)| columns as usize);
+        let x = (columns.saturating_sub(width + 2) + 1) as u16;
+        let last = &mut self.panes[pane as usize];
+        let mut frame = String::new();
+        if self.frames == 0 || last.len() != lines.len() {
+            let edge = "\u{2500}".repeat(width);
+            frame += &format!("{}\u{250c}{}\u{2510}", Goto(x, top), edge);
+            let bottom = top + lines.len() as u16 + 1;
+            frame += &format!("{}\u{2514}{}\u{2518}", Goto(x, bottom), edge);
+            last.clear();
+        }
+        for (number, line) in lines.iter().enumerate() {
+            if last.get(number) != Some(line) {
+                let y = top + number as u16 + 1;
+                frame += &format!("{}\u{2502}{}\u{2502}", Goto(x, y), line);
+            }
+        }
+        *last = lines.to_vec();
+        frame
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::board::calculate_adjacent_mines;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Somewhere to draw frames that the test can still read once the screen has them
+    #[derive(Clone, Default)]
+    pub struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        /// Everything written since the last call
+        pub fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.borrow_mut())).unwrap()
+        }
+    }
+
+    /// A 3x3 board with mines in two corners, drawn without colours or labels
+    fn board() -> Board {
+        let mut board = Board::new(3, 3, 2).unwrap();
+        board.cells[0].mines = 1;
+        board.cells[8].mines = 1;
+        calculate_adjacent_mines(&mut board);
+        board.settings.colours = false;
+        board.settings.labels = false;
+        board.settings.border = Border::Ascii;
+        board
+    }
+
+    /// A screen that draws into `output`, with `board` scrolled into place and drawn once
+    fn screen(output: &Output, board: &Board) -> Screen {
+        let mut screen = Screen::new(Box::new(output.clone()));
+        // Not centred, so that the board is in the top left whatever terminal the tests run in
+        screen.scroll(board, 1, 0, false);
+        let frame = screen.draw(board, "Playing");
+        screen.show(&frame);
+        screen
+    }
+
+    #[test]
+    fn the_first_frame_is_drawn_in_full() {
+        let output = Output::default();
+        screen(&output, &board());
+        let expected = concat!(
+            "\x1b[2J",
+            "\x1b[1;1H+-------+",
+            "\x1b[2;1H|[#]# # |",
+            "\x1b[3;1H| # # # |",
+            "\x1b[4;1H| # # # |",
+            "\x1b[5;1H+-------+\r",
+            "\x1b[6;1HPlaying",
+        );
+        assert_eq!(output.take(), expected);
+    }
+
+    #[test]
+    fn later_frames_only_draw_what_changed() {
+        let output = Output::default();
+        let mut board = board();
+        let mut screen = screen(&output, &board);
+        output.take();
+        board.reveal(0, 2);
+        let frame = screen.draw(&board, "Playing | 1");
+        screen.show(&frame);
+        // The cell after the selected one draws the closing bracket in front of it
+        let expected = concat!(
+            "\x1b[2;4H]1",
+            "\x1b[2;6H   ",
+            "\x1b[3;4H 2",
+            "\x1b[3;6H 1 ",
+            "\x1b[6;1HPlaying | 1\x1b[K",
+        );
+        assert_eq!(output.take(), expected);
+        assert_eq!(screen.draw(&board, "Playing | 1"), "");
+    }
+
+    #[test]
+    fn a_requested_redraw_draws_everything_again() {
+        let output = Output::default();
+        let board = board();
+        let mut screen = screen(&output, &board);
+        let first = output.take();
+        screen.request_redraw();
+        let frame = screen.draw(&board, "Playing");
+        screen.show(&frame);
+        assert_eq!(output.take(), first);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::board::{Board, Border, Spacing};
+use crate::coords::Notation;
+use crate::theme::Theme;
+
+/// The choices that change how the game looks and plays without changing the board itself, so
+/// that they can be changed in the middle of a game and take effect straight away
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Whether cells are drawn in colour
+    pub colours: bool,
+    /// Whether marking a flagged cell again turns it into a question mark
+    pub question_marks: bool,
+    /// Whether no more flags than mines can be placed
+    pub flag_limit: bool,
+    /// Chord numbers automatically as soon as flags satisfy them
+    pub auto_chord: bool,
+    /// Flag cells automatically once a number leaves no doubt they are mines
+    pub auto_flag: bool,
+    /// Whether moving the selection off one edge brings it back on the opposite edge
+    pub wrap_selection: bool,
+    /// How rows and columns are numbered or lettered
+    pub coordinates: Notation,
+    /// Whether the rows and columns are labelled with their numbers
+    pub labels: bool,
+    /// Whether the board is drawn in the middle of the terminal rather than its top left corner
+    pub centre: bool,
+    /// Whether a board too big for the terminal is scrolled to follow the selected cell. With
+    /// this off, the whole board has to fit.
+    pub scroll: bool,
+    pub border: Border,
+    pub spacing: Spacing,
+    /// The colours the board is drawn in
+    pub theme: Theme,
+    /// Whether each number is drawn in its own style as well as its own colour, so that telling
+    /// them apart doesn't rely on colour alone
+    pub number_styles: bool,
+    /// Whether the cells around the selected one are tinted, to make counting them easier
+    pub neighbourhood: bool,
+    /// Whether the row and column of the selected cell are tinted, to line it up with the labels
+    pub crosshair: bool,
+    /// Whether big openings appear all at once instead of spreading out from the revealed cell
+    pub reduce_motion: bool,
+    /// Whether numbers show how many of their mines haven't been flagged yet rather than how many
+    /// there are
+    pub effective_numbers: bool,
+    /// Whether a time attack can be paused, stopping the countdown until it is resumed. Games
+    /// take this when they start, and keep it.
+    pub pause_countdown: bool,
+    /// Whether the terminal bell rings when a key can't do anything
+    pub bell: bool,
+    /// Whether a key that can't do anything says why on the message line
+    pub flash: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            colours: true,
+            question_marks: false,
+            flag_limit: false,
+            auto_chord: false,
+            auto_flag: false,
+            wrap_selection: false,
+            coordinates: Notation::ZeroBased,
+            labels: true,
+            centre: true,
+            scroll: true,
+            border: Border::Unicode,
+            spacing: Spacing::Normal,
+            theme: Theme::default(),
+            number_styles: false,
+            neighbourhood: false,
+            crosshair: false,
+            reduce_motion: false,
+            effective_numbers: false,
+            pause_countdown: true,
+            bell: true,
+            flash: true,
+        }
+    }
+}
+
+/// A line of the settings screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    /// Shown so the player can see it, but only a new board can change it
+    BoardSize,
+    Colours,
+    QuestionMarks,
+    FlagLimit,
+    AutoChord,
+    AutoFlag,
+    WrapSelection,
+    PauseCountdown,
+    Coordinates,
+    Labels,
+    Centre,
+    Scroll,
+    Border,
+    Spacing,
+    Glyphs,
+    CursorStyle,
+    Theme,
+    NumberStyles,
+    Neighbourhood,
+    Crosshair,
+    ReduceMotion,
+    EffectiveNumbers,
+    Bell,
+    Flash,
+}
+
+pub const SETTINGS: [Setting; 24] = [
+    Setting::BoardSize,
+    Setting::Colours,
+    Setting::QuestionMarks,
+    Setting::FlagLimit,
+    Setting::AutoChord,
+    Setting::AutoFlag,
+    Setting::WrapSelection,
+    Setting::PauseCountdown,
+    Setting::Coordinates,
+    Setting::Labels,
+    Setting::Centre,
+    Setting::Scroll,
+    Setting::Border,
+    Setting::Spacing,
+    Setting::Glyphs,
+    Setting::CursorStyle,
+    Setting::Theme,
+    Setting::NumberStyles,
+    Setting::Neighbourhood,
+    Setting::Crosshair,
+    Setting::ReduceMotion,
+    Setting::EffectiveNumbers,
+    Setting::Bell,
+    Setting::Flash,
+];
+
+impl Setting {
+    pub fn label(self) -> &'static str {
+        match self {
+            Setting::BoardSize => "Board size",
+            Setting::Colours => "Colours",
+            Setting::QuestionMarks => "Question marks",
+            Setting::FlagLimit => "Flag limit",
+            Setting::AutoChord => "Auto chord",
+            Setting::AutoFlag => "Auto flag",
+            Setting::WrapSelection => "Wrap around edges",
+            Setting::PauseCountdown => "Pause time attacks",
+            Setting::Coordinates => "Coordinates",
+            Setting::Labels => "Row and column labels",
+            Setting::Centre => "Centre the board",
+            Setting::Scroll => "Scroll big boards",
+            Setting::Border => "Border",
+            Setting::Spacing => "Cell spacing",
+            Setting::Glyphs => "Cell glyphs",
+            Setting::CursorStyle => "Cursor",
+            Setting::Theme => "Colour theme",
+            Setting::NumberStyles => "Styled numbers",
+            Setting::Neighbourhood => "Highlight neighbours",
+            Setting::Crosshair => "Highlight row and column",
+            Setting::ReduceMotion => "Reduce motion",
+            Setting::EffectiveNumbers => "Subtract flags from numbers",
+            Setting::Bell => "Bell on invalid moves",
+            Setting::Flash => "Explain invalid moves",
+        }
+    }
+
+    /// Whether changing the setting means starting a new board
+    pub fn needs_new_board(self) -> bool {
+        self == Setting::BoardSize
+    }
+
+    /// How the setting is currently set, shown after its label
+    pub fn value(self, board: &Board) -> String {
+        let settings = &board.settings;
+        let on = match self {
+            Setting::BoardSize if board.depth > 1 => {
+                let (width, height, depth) = (board.width, board.height, board.depth);
+                return format!("{}x{}x{}, {} mines", width, height, depth, board.mines);
+            }
+            Setting::BoardSize => {
+                return format!("{}x{}, {} mines", board.width, board.height, board.mines)
+            }
+            Setting::Border => return settings.border.name().to_string(),
+            Setting::Spacing => return settings.spacing.name().to_string(),
+            Setting::Coordinates => return settings.coordinates.name().to_string(),
+            Setting::Glyphs => return settings.theme.glyphs.name().to_string(),
+            Setting::CursorStyle => return settings.theme.cursor_style.name().to_string(),
+            Setting::Theme => return settings.theme.name.clone(),
+            Setting::Colours => settings.colours,
+            Setting::QuestionMarks => settings.question_marks,
+            Setting::FlagLimit => settings.flag_limit,
+            Setting::AutoChord => settings.auto_chord,
+            Setting::AutoFlag => settings.auto_flag,
+            Setting::WrapSelection => settings.wrap_selection,
+            Setting::PauseCountdown => settings.pause_countdown,
+            Setting::Labels => settings.labels,
+            Setting::Centre => settings.centre,
+            Setting::Scroll => settings.scroll,
+            Setting::NumberStyles => settings.number_styles,
+            Setting::Neighbourhood => settings.neighbourhood,
+            Setting::Crosshair => settings.crosshair,
+            Setting::ReduceMotion => settings.reduce_motion,
+            Setting::EffectiveNumbers => settings.effective_numbers,
+            Setting::Bell => settings.bell,
+            Setting::Flash => settings.flash,
+        };
+        if on { "on" } else { "off" }.to_string()
+    }
+
+    /// Moves the setting on to its next value. Switches flip, and the rest go through each choice
+    /// in turn, the themes being the ones in `themes`.
+    pub fn change(self, settings: &mut Settings, themes: &[Theme]) {
+        let switch = match self {
+            Setting::BoardSize => return,
+            Setting::Border => {
+                settings.border = settings.border.next();
+                return;
+            }
+            Setting::Spacing => {
+                settings.spacing = settings.spacing.next();
+                return;
+            }
+            Setting::Coordinates => {
+                settings.coordinates = settings.coordinates.next();
+                return;
+            }
+            Setting::Glyphs => {
+                settings.theme.glyphs = settings.theme.glyphs.next();
+                return;
+            }
+            Setting::CursorStyle => {
+                let theme = &mut settings.theme;
+                theme.cursor_style = theme.cursor_style.next();
+                return;
+            }
+            Setting::Theme => {
+                let current = themes
+                    .iter()
+                    .position(|theme| theme.name == settings.theme.name);
+                let next = current.map_or(0, |current| (current + 1) % themes.len());
+                if let Some(theme) = themes.get(next) {
+                    settings.theme = Theme {
+                        glyphs: settings.theme.glyphs,
+                        cursor_style: settings.theme.cursor_style,
+                        ..theme.clone()
+                    };
+                }
+                return;
+            }
+            Setting::Colours => &mut settings.colours,
+            Setting::QuestionMarks => &mut settings.question_marks,
+            Setting::FlagLimit => &mut settings.flag_limit,
+            Setting::AutoChord => &mut settings.auto_chord,
+            Setting::AutoFlag => &mut settings.auto_flag,
+            Setting::WrapSelection => &mut settings.wrap_selection,
+            Setting::PauseCountdown => &mut settings.pause_countdown,
+            Setting::Labels => &mut settings.labels,
+            Setting::Centre => &mut settings.centre,
+            Setting::Scroll => &mut settings.scroll,
+            Setting::NumberStyles => &mut settings.number_styles,
+            Setting::Neighbourhood => &mut settings.neighbourhood,
+            Setting::Crosshair => &mut settings.crosshair,
+            Setting::ReduceMotion => &mut settings.reduce_motion,
+            Setting::EffectiveNumbers => &mut settings.effective_numbers,
+            Setting::Bell => &mut settings.bell,
+            Setting::Flash => &mut settings.flash,
+        };
+        *switch = !*switch;
+    }
+}
@@ -0,0 +1,184 @@
+use crate::board::Board;
+
+/// What the solver has worked out about a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Known {
+    Unknown,
+    Mine,
+    /// Safe and opened, so its number can be read
+    Open,
+}
+
+/// A revealed number and the covered cells around it that it says something about
+struct Constraint {
+    /// Sorted, so that one constraint's cells can be checked against another's
+    cells: Vec<usize>,
+    /// How many of `cells` are mines
+    mines: usize,
+}
+
+/// Whether the solver can work out boards like this one. It can't when a cell can hold more than
+/// one mine or when there are anti-mines, as a number no longer says how many cells are mines.
+pub fn can_solve(board: &Board) -> bool {
+    board.max_mines_per_cell == 1 && board.anti_mines == 0
+}
+
+/// Plays the board the way a careful player would, starting by revealing `start`, and returns
+/// whether every safe cell can be opened without ever having to guess. Only the numbers it
+/// opens are looked at, never where the mines are, and the board itself isn't changed. Boards
+/// that `can_solve` turns down count as needing guesses.
+pub fn is_solvable(board: &Board, start: usize) -> bool {
+    if !can_solve(board) || board.cells[start].is_mine() {
+        return false;
+    }
+    let neighbours: Vec<Vec<usize>> = (0..board.cells.len())
+        .map(|index| board.neighbours(index))
+        .collect();
+    let mut known = vec![Known::Unknown; board.cells.len()];
+    let mut unopened = board.safe_cells;
+    let mut to_open = vec![start];
+    loop {
+        // Opening a cell with no mines around it opens everything around it too
+        while let Some(index) = to_open.pop() {
+            if known[index] != Known::Unknown {
+                continue;
+            }
+            known[index] = Known::Open;
+            unopened -= 1;
+            if board.cells[index].adjacent_mines == 0 {
+                to_open.extend(&neighbours[index]);
+            }
+        }
+        if unopened == 0 {
+            return true;
+        }
+        let mut constraints = Vec::new();
+        for (index, &state) in known.iter().enumerate() {
+            if state != Known::Open {
+                continue;
+            }
+            let mut cells = Vec::new();
+            let mut mines = board.cells[index].adjacent_mines as usize;
+            for &near in &neighbours[index] {
+                match known[near] {
+                    Known::Unknown => cells.push(near),
+                    Known::Mine => mines -= 1,
+                    Known::Open => {}
+                }
+            }
+            if !cells.is_empty() {
+                cells.sort_unstable();
+                constraints.push(Constraint { cells, mines });
+            }
+        }
+        let mut mines = Vec::new();
+        // A number whose mines are all accounted for, or whose covered cells are all mines
+        for constraint in &constraints {
+            if constraint.mines == 0 {
+                to_open.extend(&constraint.cells);
+            } else if constraint.mines == constraint.cells.len() {
+                mines.extend(&constraint.cells);
+            }
+        }
+        // When one number's covered cells are all around another number too, the cells only
+        // the second one touches hold the difference between them
+        if to_open.is_empty() && mines.is_empty() {
+            for small in &constraints {
+                for large in &constraints {
+                    if large.cells.len() <= small.cells.len() || large.mines < small.mines {
+                        continue;
+                    }
+                    if !small
+                        .cells
+                        .iter()
+                        .all(|cell| large.cells.binary_search(cell).is_ok())
+                    {
+                        continue;
+                    }
+                    let rest = large
+                        .cells
+                        .iter()
+                        .filter(|cell| small.cells.binary_search(cell).is_err());
+                    let difference = large.mines - small.mines;
+                    if difference == 0 {
+                        to_open.extend(rest);
+                    } else if difference == large.cells.len() - small.cells.len() {
+                        mines.extend(rest);
+                    }
+                }
+            }
+        }
+        // Failing that, the number of mines left might settle the rest of the board
+        if to_open.is_empty() && mines.is_empty() {
+            let found = known.iter().filter(|&&state| state == Known::Mine).count();
+            let unknown: Vec<usize> = (0..known.len())
+                .filter(|&index| known[index] == Known::Unknown)
+                .collect();
+            if found == board.mines {
+                to_open = unknown;
+            } else if board.mines - found == unknown.len() {
+                mines = unknown;
+            }
+        }
+        if to_open.is_empty() && mines.is_empty() {
+            return false;
+        }
+        for index in mines {
+            known[index] = Known::Mine;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::calculate_adjacent_mines;
+
+    /// A board laid out like `rows`, with a mine wherever there is a `*`
+    fn board(rows: &[&str]) -> Board {
+        let mines = rows.iter().map(|row| row.matches('*').count()).sum();
+        let mut board = Board::new(rows[0].len(), rows.len(), mines).unwrap();
+        for (cell, c) in board.cells.iter_mut().zip(rows.concat().chars()) {
+            cell.mines = (c == '*') as u8;
+        }
+        calculate_adjacent_mines(&mut board);
+        board
+    }
+
+    #[test]
+    fn a_one_two_one_is_worked_out_from_the_numbers_it_shares() {
+        // The 1s and the 2 each have more covered cells than mines, so only comparing them
+        // shows where the mines are
+        let board = board(&["...", "...", "...", "*.*"]);
+        assert!(is_solvable(&board, 0));
+    }
+
+    #[test]
+    fn a_fifty_fifty_needs_a_guess() {
+        let board = board(&["..", "..", "*."]);
+        assert!(!is_solvable(&board, 0));
+    }
+
+    #[test]
+    fn the_mines_left_can_settle_cells_no_number_touches() {
+        // The corner is walled off by mines, so only the count says whether it is one too
+        assert!(is_solvable(&board(&["....", "....", "..**", "..*."]), 0));
+        assert!(is_solvable(&board(&["....", "....", "..**", "..**"]), 0));
+    }
+
+    #[test]
+    fn starting_on_a_mine_never_works() {
+        let board = board(&["*..", "...", "..."]);
+        assert!(!is_solvable(&board, 0));
+        assert!(is_solvable(&board, 8));
+    }
+
+    #[test]
+    fn boards_with_anti_mines_or_shared_cells_are_turned_down() {
+        let mut board = board(&["...", "...", "..*"]);
+        assert!(is_solvable(&board, 0));
+        board.anti_mines = 50;
+        assert!(!can_solve(&board));
+        assert!(!is_solvable(&board, 0));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::rules::Rules;
+use crate::timer::format_duration;
+use crate::title::Difficulty;
+use crate::topology::Topology;
+use std::time::Duration;
+
+/// How many columns the bar showing how much of the board has been cleared takes up inside its
+/// brackets, and the fewest it can be squeezed down to before it is left out
+pub const PROGRESS_BAR: usize = 10;
+pub const MIN_PROGRESS_BAR: usize = 3;
+
+/// A bar like `[#####-----]` showing `percentage`, `width` columns wide inside the brackets
+pub fn progress_bar(percentage: usize, width: usize) -> String {
+    let filled = percentage.min(100) * width / 100;
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+/// What the player did over a game, counted as it happens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Cells revealed one at a time, not counting the ones that opened along with them
+    pub reveals: usize,
+    pub chords: usize,
+    /// Flags put down, including ones that were later taken away
+    pub flags_placed: usize,
+    /// Cells that opened because a reveal spread out from an empty cell
+    pub cascade_cells: usize,
+}
+
+/// Everything worth knowing about a finished game. The game over box shows this, and anything
+/// that keeps a record of games should keep this too, so the two always agree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub won: bool,
+    pub time: Duration,
+    pub width: usize,
+    pub height: usize,
+    /// How many layers the board had, which is 1 unless it was layered
+    pub depth: usize,
+    pub mines: usize,
+    /// What percentage of the cells with mines held anti-mines
+    pub anti_mines: usize,
+    pub per_cell: u8,
+    pub topology: Topology,
+    pub radius: usize,
+    pub lives: usize,
+    /// The difficulty the board was picked from, unless it was a custom one
+    pub preset: Option<&'static str>,
+    /// What the mines' places came from, so the board can be played again with `--seed`
+    pub seed: u64,
+    /// What the game was played by, so that these times are kept apart from ones played by
+    /// other rules
+    pub rules: Rules,
+    /// Whether the game was lost by the time limit running out rather than by a mine
+    pub out_of_time: bool,
+    /// How many of the safe cells were revealed, out of 100
+    pub cleared: usize,
+    pub stats: Stats,
+}
+
+impl Summary {
+    /// The summary as lines of text, starting with the result
+    pub fn lines(&self) -> Vec<String> {
+        let mut board = format!("{}x{}", self.width, self.height);
+        if self.depth > 1 {
+            board += &format!("x{}", self.depth);
+        }
+        board += &format!(", {} mines", self.mines);
+        // Only the difficulty's own board goes by its name
+        let standard = |preset: &&str| {
+            Difficulty::from_name(preset).is_some_and(|difficulty| {
+                self.depth == 1 && difficulty.is_board(self.width, self.height, self.mines)
+            })
+        };
+        if let Some(preset) = self.preset.filter(standard) {
+            board = format!("{} ({})", preset, board);
+        }
+        if self.anti_mines > 0 {
+            board += &format!(", {}% anti-mines", self.anti_mines);
+        }
+        if self.per_cell > 1 {
+            board += &format!(", up to {} a cell", self.per_cell);
+        }
+        if self.topology != Topology::Square {
+            board += &format!(", {} grid", self.topology.name());
+        }
+        if self.radius > 1 {
+            board += &format!(", neighbours up to {} away", self.radius);
+        }
+        if self.lives > 1 {
+            board += &format!(", {} lives", self.lives);
+        }
+        if let Some(every) = self.rules.moving_mines {
+            board += &format!(", a mine moved every {} reveals", every);
+        }
+        if let Some(fog) = self.rules.fog {
+            board += &format!(", fog of war {} cells out", fog);
+        }
+        if self.rules.no_flags {
+            board += ", no flags (NF)";
+        }
+        if let Some(limit) = self.rules.time_limit() {
+            board += &format!(", {} to clear it", format_duration(limit));
+        }
+        let stats = &self.stats;
+        let mut lines = vec![match (self.won, self.out_of_time) {
+            (true, _) => "You win!".to_string(),
+            (false, true) => "Out of time!".to_string(),
+            (false, false) => "You lost!".to_string(),
+        }];
+        if !self.rules.hide_timer {
+            lines.push(format!("Time: {}", format_duration(self.time)));
+        }
+        if self.rules.is_zen() {
+            lines.push("Played in zen mode, so nothing was kept".to_string());
+        }
+        lines.extend([
+            format!("Board: {}", board),
+            format!("Seed: {}", self.seed),
+            format!(
+                "Cleared: {} {}%",
+                progress_bar(self.cleared, PROGRESS_BAR),
+                self.cleared
+            ),
+            format!("Reveals: {}", stats.reveals),
+            format!("Chords: {}", stats.chords),
+            format!("Flags placed: {}", stats.flags_placed),
+            format!("Opened by cascades: {}", stats.cascade_cells),
+        ]);
+        lines
+    }
+}
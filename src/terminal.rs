@@ -0,0 +1,82 @@
+use std::io::{self, Stdout, Write};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use termion::input::MouseTerminal;
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::screen::AlternateScreen;
+
+/// The terminal while the game is being played. It lives here rather than in `main` so that the
+/// panic hook can reach it too.
+static TERMINAL: Mutex<Option<TerminalGuard>> = Mutex::new(None);
+
+/// Holds the terminal in the state the game is played in: raw mode, on the alternate screen,
+/// with mouse reporting on and the cursor hidden. Dropping it puts everything back the way it
+/// was, in the reverse order, so the player's scrollback reappears untouched however the game
+/// ends.
+pub struct TerminalGuard {
+    stdout: MouseTerminal<AlternateScreen<RawTerminal<Stdout>>>,
+}
+
+impl TerminalGuard {
+    pub fn new() -> io::Result<TerminalGuard> {
+        let stdout = MouseTerminal::from(AlternateScreen::from(io::stdout().into_raw_mode()?));
+        let mut guard = TerminalGuard { stdout };
+        write!(
+            guard.stdout,
+            "{}{}{}",
+            termion::clear::All,
+            termion::cursor::Hide,
+            termion::cursor::Goto(1, 1)
+        )?;
+        guard.stdout.flush()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // The fields are dropped after this, turning off the mouse, leaving the alternate screen
+        // and then raw mode
+        let _ = write!(self.stdout, "{}", termion::cursor::Show);
+        let _ = self.stdout.flush();
+    }
+}
+
+/// Locks the terminal without waiting, so that a panic while it was locked can't leave the panic
+/// hook stuck
+fn lock() -> Option<MutexGuard<'static, Option<TerminalGuard>>> {
+    match TERMINAL.try_lock() {
+        Ok(terminal) => Some(terminal),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Sets the terminal up for playing until `restore` is called
+pub fn enter() -> io::Result<()> {
+    let guard = TerminalGuard::new()?;
+    if let Some(mut terminal) = lock() {
+        *terminal = Some(guard);
+    }
+    Ok(())
+}
+
+/// Puts the terminal back the way it was before `enter`. Every way out of the game goes through
+/// here, and calling it again does nothing.
+pub fn restore() {
+    // Dropped after the lock is released
+    let guard = lock().and_then(|mut terminal| terminal.take());
+    drop(guard);
+    // Leaving the alternate screen is only written to the buffer as the guard is dropped, and it
+    // has to reach the terminal before anything else is printed
+    let _ = io::stdout().flush();
+}
+
+/// Makes panics restore the terminal before printing their message, so that the message comes
+/// out readable and the shell is left usable afterwards
+pub fn install_panic_hook() {
+    let print_panic = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        print_panic(info);
+    }));
+}
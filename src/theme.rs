@@ -0,0 +1,495 @@
+use termion::color::{self, AnsiValue};
+
+/// A colour from the terminal's palette, or `None` for the terminal's own colour
+pub type Colour = Option<AnsiValue>;
+
+/// The colours everything is drawn in, as indexes into the terminal's palette
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// What the theme is called in the config file and on the settings screen
+    pub name: String,
+    /// The colour of each number from 1 to 8. Boards where cells hold several mines can have
+    /// larger numbers, which use the colour for 8.
+    pub numbers: [Colour; 8],
+    pub mine: Colour,
+    pub flag: Colour,
+    /// Flags that turn out to be wrong once the game is lost
+    pub wrong_flag: Colour,
+    /// The mine that was set off
+    pub detonated: Colour,
+    pub covered: Colour,
+    /// The colour of the faint dot drawn in revealed cells with no mines around them. Without
+    /// one they are left blank.
+    pub empty: Colour,
+    /// The background of the selected cell, when the cursor is drawn as a background
+    pub cursor: Colour,
+    /// The background of the cells around the selected one, when they are highlighted
+    pub neighbourhood: Colour,
+    /// The background of the row and column of the selected cell, when they are highlighted
+    pub crosshair: Colour,
+    /// The background of the cells a chord would open, and of the same cells when the number
+    /// doesn't have as many flags around it as it needs yet
+    pub preview: Colour,
+    pub preview_warning: Colour,
+    /// The frame around the board
+    pub border: Colour,
+    /// The lines under the board
+    pub status: Colour,
+    /// The characters the cells are drawn with
+    pub glyphs: GlyphSet,
+    /// How the selected cell stands out from the rest
+    pub cursor_style: CursorStyle,
+}
+
+/// Where a theme's colours are written in the config file, for the error when one isn't valid
+#[derive(Debug)]
+pub enum ThemeError {
+    UnknownField(String),
+    UnknownBase(String),
+    BadColour { field: String, value: String },
+}
+
+impl Default for Theme {
+    /// The colours of the classic game
+    fn default() -> Theme {
+        let ansi = |value| Some(AnsiValue(value));
+        Theme {
+            name: "classic".to_string(),
+            // Blue, green, red, dark blue, maroon, cyan, black and grey
+            numbers: [
+                ansi(12),
+                ansi(2),
+                ansi(9),
+                ansi(4),
+                ansi(1),
+                ansi(6),
+                ansi(0),
+                ansi(8),
+            ],
+            mine: ansi(13),
+            flag: ansi(11),
+            wrong_flag: ansi(9),
+            detonated: ansi(9),
+            covered: None,
+            cursor: None,
+            empty: None,
+            neighbourhood: ansi(236),
+            crosshair: ansi(234),
+            preview: ansi(22),
+            preview_warning: ansi(52),
+            border: None,
+            status: None,
+            glyphs: GlyphSet::for_locale(),
+            cursor_style: CursorStyle::Inverse,
+        }
+    }
+}
+
+/// The themes that come with the game, classic first
+pub fn builtin_themes() -> Vec<Theme> {
+    let ansi = |value| Some(AnsiValue(value));
+    let classic = Theme::default();
+    let dark = Theme {
+        name: "dark".to_string(),
+        // Brighter versions of the classic colours, so that none of them fade into black
+        numbers: [
+            ansi(75),
+            ansi(76),
+            ansi(203),
+            ansi(111),
+            ansi(174),
+            ansi(44),
+            ansi(252),
+            ansi(245),
+        ],
+        mine: ansi(201),
+        flag: ansi(226),
+        wrong_flag: ansi(196),
+        detonated: ansi(196),
+        covered: ansi(242),
+        cursor: ansi(238),
+        neighbourhood: ansi(235),
+        crosshair: ansi(234),
+        preview: ansi(22),
+        preview_warning: ansi(88),
+        border: ansi(244),
+        status: ansi(250),
+        ..classic.clone()
+    };
+    let light = Theme {
+        name: "light".to_string(),
+        // Darker versions of the classic colours, so that none of them fade into white
+        numbers: [
+            ansi(21),
+            ansi(28),
+            ansi(160),
+            ansi(18),
+            ansi(88),
+            ansi(30),
+            ansi(16),
+            ansi(242),
+        ],
+        mine: ansi(90),
+        flag: ansi(166),
+        wrong_flag: ansi(160),
+        detonated: ansi(160),
+        covered: ansi(246),
+        cursor: ansi(253),
+        neighbourhood: ansi(255),
+        crosshair: ansi(231),
+        preview: ansi(151),
+        preview_warning: ansi(217),
+        border: ansi(244),
+        status: ansi(236),
+        ..classic.clone()
+    };
+    let solarized = Theme {
+        name: "solarized".to_string(),
+        // Blue, green, red, violet, magenta, cyan, base01 and orange from the solarized palette
+        numbers: [
+            ansi(33),
+            ansi(64),
+            ansi(160),
+            ansi(61),
+            ansi(125),
+            ansi(37),
+            ansi(240),
+            ansi(166),
+        ],
+        mine: ansi(125),
+        flag: ansi(136),
+        wrong_flag: ansi(160),
+        detonated: ansi(160),
+        covered: ansi(241),
+        empty: ansi(239),
+        cursor: ansi(236),
+        neighbourhood: ansi(235),
+        crosshair: ansi(234),
+        preview: ansi(29),
+        preview_warning: ansi(124),
+        border: ansi(240),
+        status: ansi(245),
+        ..classic.clone()
+    };
+    let colourblind = Theme {
+        name: "colourblind".to_string(),
+        // The Okabe-Ito palette, which stays apart for the common kinds of colour blindness.
+        // Reds and greens are never both used, so mines and flags differ in brightness too.
+        numbers: [
+            ansi(25),
+            ansi(178),
+            ansi(166),
+            ansi(74),
+            ansi(175),
+            ansi(36),
+            ansi(250),
+            ansi(245),
+        ],
+        mine: ansi(175),
+        flag: ansi(221),
+        wrong_flag: ansi(166),
+        detonated: ansi(166),
+        covered: None,
+        // A grey background shows the selection whatever colours can be told apart
+        cursor: ansi(240),
+        neighbourhood: ansi(237),
+        crosshair: ansi(235),
+        // Blue and orange are the pair that stays apart best
+        preview: ansi(25),
+        preview_warning: ansi(130),
+        border: None,
+        status: None,
+        ..classic.clone()
+    };
+    vec![classic, dark, light, solarized, colourblind]
+}
+
+/// The styles that tell numbers apart without colour, when numbers are drawn with styles
+pub fn number_style(number: i16) -> String {
+    let (bold, italic, underline) = match number.clamp(1, 8) {
+        1 => (false, false, false),
+        2 => (true, false, false),
+        3 => (false, false, true),
+        4 => (true, false, true),
+        5 => (false, true, false),
+        6 => (false, true, true),
+        7 => (true, true, false),
+        _ => (true, true, true),
+    };
+    let mut style = String::new();
+    for (on, code) in [
+        (bold, termion::style::Bold.to_string()),
+        (italic, termion::style::Italic.to_string()),
+        (underline, termion::style::Underline.to_string()),
+    ] {
+        if on {
+            style += &code;
+        }
+    }
+    style
+}
+
+impl Theme {
+    /// The colour of a number, which must be at least 1
+    pub fn number(&self, number: i16) -> Colour {
+        let index = (number.clamp(1, 8) - 1) as usize;
+        self.numbers[index]
+    }
+
+    /// Starts a theme of the player's own from one of the built in ones
+    pub fn based_on(name: &str, base: &str) -> Result<Theme, ThemeError> {
+        let base = builtin_themes()
+            .into_iter()
+            .find(|theme| theme.name == base)
+            .ok_or_else(|| ThemeError::UnknownBase(base.to_string()))?;
+        Ok(Theme {
+            name: name.to_string(),
+            ..base
+        })
+    }
+
+    /// Sets one of the colours from the config file. Numbers are `number_1` to `number_8`.
+    pub fn set(&mut self, field: &str, value: &str) -> Result<(), ThemeError> {
+        let colour = parse_colour(value).ok_or_else(|| ThemeError::BadColour {
+            field: field.to_string(),
+            value: value.to_string(),
+        })?;
+        let slot = match field {
+            "mine" => &mut self.mine,
+            "flag" => &mut self.flag,
+            "wrong_flag" => &mut self.wrong_flag,
+            "detonated" => &mut self.detonated,
+            "covered" => &mut self.covered,
+            "empty" => &mut self.empty,
+            "cursor" => &mut self.cursor,
+            "neighbourhood" => &mut self.neighbourhood,
+            "crosshair" => &mut self.crosshair,
+            "preview" => &mut self.preview,
+            "preview_warning" => &mut self.preview_warning,
+            "border" => &mut self.border,
+            "status" => &mut self.status,
+            _ => match field
+                .strip_prefix("number_")
+                .and_then(|number| number.parse::<usize>().ok())
+            {
+                Some(number @ 1..=8) => &mut self.numbers[number - 1],
+                _ => return Err(ThemeError::UnknownField(field.to_string())),
+            },
+        };
+        *slot = colour;
+        Ok(())
+    }
+}
+
+/// The names colours can be given by in the config file, in palette order
+const COLOUR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+/// Reads a colour from the config file, which is either one of the names above, `default` for the
+/// terminal's own colour, or an index into the 256 colour palette
+fn parse_colour(value: &str) -> Option<Colour> {
+    if value == "default" {
+        return Some(None);
+    }
+    let index = match COLOUR_NAMES.iter().position(|&name| name == value) {
+        Some(index) => index as u8,
+        None => value.parse::<u8>().ok()?,
+    };
+    Some(Some(AnsiValue(index)))
+}
+
+/// The characters cells are drawn with. Every glyph in a set takes up the same number of columns,
+/// which is what the board is padded to so that the columns stay lined up.
+#[derive(Debug)]
+pub struct Glyphs {
+    pub flag: &'static str,
+    pub mine: &'static str,
+    /// The flag marking an anti-mine, and the anti-mine itself
+    pub anti_flag: &'static str,
+    pub anti_mine: &'static str,
+    pub covered: &'static str,
+    pub question: &'static str,
+    /// Drawn in empty cells when the theme gives them a colour
+    pub empty: &'static str,
+    /// The mine that was set off
+    pub detonated: &'static str,
+    /// Flags that turn out to be wrong once the game is lost
+    pub wrong_flag: &'static str,
+    /// How many columns each glyph takes up on the terminal
+    pub width: usize,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    flag: "\u{2691}",
+    mine: "\u{2736}",
+    anti_flag: "\u{2690}",
+    anti_mine: "\u{229d}",
+    covered: "\u{00b7}",
+    question: "?",
+    empty: "\u{00b7}",
+    detonated: "@",
+    wrong_flag: "\u{2717}",
+    width: 1,
+};
+
+/// Emoji are two columns wide in most terminals, so the numbers are padded to match
+const EMOJI: Glyphs = Glyphs {
+    flag: "\u{1f6a9}",
+    mine: "\u{1f4a3}",
+    anti_flag: "\u{1f3f4}",
+    anti_mine: "\u{1f535}",
+    covered: "\u{2b1c}",
+    question: "\u{2753}",
+    empty: " \u{00b7}",
+    detonated: "\u{1f4a5}",
+    wrong_flag: "\u{274c}",
+    width: 2,
+};
+
+/// For terminals that can't show anything but ASCII
+const ASCII: Glyphs = Glyphs {
+    flag: "F",
+    mine: "*",
+    anti_flag: "A",
+    anti_mine: "o",
+    covered: "#",
+    question: "?",
+    empty: ".",
+    detonated: "@",
+    wrong_flag: "X",
+    width: 1,
+};
+
+/// Which set of glyphs the board is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphSet {
+    Unicode,
+    Emoji,
+    Ascii,
+}
+
+impl GlyphSet {
+    /// Unicode when the locale says the terminal takes UTF-8, and ASCII otherwise
+    pub fn for_locale() -> GlyphSet {
+        // The first of these that is set decides the character set, as with the C library
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if locale.contains("utf-8") || locale.contains("utf8") {
+            GlyphSet::Unicode
+        } else {
+            GlyphSet::Ascii
+        }
+    }
+
+    pub fn glyphs(self) -> &'static Glyphs {
+        match self {
+            GlyphSet::Unicode => &UNICODE,
+            GlyphSet::Emoji => &EMOJI,
+            GlyphSet::Ascii => &ASCII,
+        }
+    }
+
+    /// The name the set goes by in the config file and on the settings screen
+    pub fn name(self) -> &'static str {
+        match self {
+            GlyphSet::Unicode => "unicode",
+            GlyphSet::Emoji => "emoji",
+            GlyphSet::Ascii => "ascii",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GlyphSet> {
+        [GlyphSet::Unicode, GlyphSet::Emoji, GlyphSet::Ascii]
+            .into_iter()
+            .find(|set| set.name() == name)
+    }
+
+    /// The set after this one, coming back round to the first
+    pub fn next(self) -> GlyphSet {
+        match self {
+            GlyphSet::Unicode => GlyphSet::Emoji,
+            GlyphSet::Emoji => GlyphSet::Ascii,
+            GlyphSet::Ascii => GlyphSet::Unicode,
+        }
+    }
+}
+
+/// How the selected cell is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorStyle {
+    Bold,
+    /// Swaps the cell's colours, which shows up on any terminal and keeps the number's colour
+    Inverse,
+    /// The theme's cursor colour behind the cell, or inverse video for themes without one
+    Background,
+}
+
+impl CursorStyle {
+    /// The name the style goes by in the config file and on the settings screen
+    pub fn name(self) -> &'static str {
+        match self {
+            CursorStyle::Bold => "bold",
+            CursorStyle::Inverse => "inverse",
+            CursorStyle::Background => "background",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CursorStyle> {
+        [
+            CursorStyle::Bold,
+            CursorStyle::Inverse,
+            CursorStyle::Background,
+        ]
+        .into_iter()
+        .find(|style| style.name() == name)
+    }
+
+    /// The style after this one, coming back round to the first
+    pub fn next(self) -> CursorStyle {
+        match self {
+            CursorStyle::Bold => CursorStyle::Inverse,
+            CursorStyle::Inverse => CursorStyle::Background,
+            CursorStyle::Background => CursorStyle::Bold,
+        }
+    }
+
+    /// The escape codes that start and end the style, for a theme whose cursor colour is
+    /// `background`. Each one ends only what it started, so the cell's own colour carries on
+    /// inside it and nothing is left over for the next cell.
+    pub fn codes(self, background: Colour) -> (String, String) {
+        match (self, background) {
+            (CursorStyle::Bold, _) => (
+                termion::style::Bold.to_string(),
+                termion::style::Reset.to_string(),
+            ),
+            (CursorStyle::Background, Some(colour)) => (
+                color::Bg(colour).to_string(),
+                color::Bg(color::Reset).to_string(),
+            ),
+            (CursorStyle::Inverse | CursorStyle::Background, _) => (
+                termion::style::Invert.to_string(),
+                termion::style::NoInvert.to_string(),
+            ),
+        }
+    }
+}
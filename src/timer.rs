@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+/// Keeps track of how long a game has been played for. The time is added up a stretch at a time,
+/// so pausing and resuming doesn't lose any of it to rounding.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timer {
+    /// The time counted before the current stretch began
+    elapsed: Duration,
+    /// When the current stretch began, if the timer is running
+    since: Option<Instant>,
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer::default()
+    }
+
+    /// Starts the timer, or keeps it going if it is already running
+    pub fn start(&mut self) {
+        if self.since.is_none() {
+            self.since = Some(Instant::now());
+        }
+    }
+
+    /// Stops the timer, keeping the time counted so far
+    pub fn stop(&mut self) {
+        if let Some(since) = self.since.take() {
+            self.elapsed += since.elapsed();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.since.is_some()
+    }
+
+    /// Whether the timer has counted any time at all, which is what decides whether resuming a
+    /// paused game should start it again
+    pub fn has_started(&self) -> bool {
+        self.is_running() || self.elapsed > Duration::ZERO
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match self.since {
+            Some(since) => self.elapsed + since.elapsed(),
+            None => self.elapsed,
+        }
+    }
+}
+
+/// Shows a length of time as minutes and seconds, like `3:07`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
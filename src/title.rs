@@ -0,0 +1,770 @@
+use crate::board::*;
+use crate::keys::*;
+use crate::menu::*;
+use crate::rules::Rules;
+use crate::timer::format_duration;
+use crate::topology::Topology;
+use termion::event::Key;
+
+/// A board size and mine count to pick from on the title screen
+pub struct Difficulty {
+    pub name: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+}
+
+pub const DIFFICULTIES: [Difficulty; 3] = [
+    Difficulty {
+        name: "Beginner",
+        width: 9,
+        height: 9,
+        mines: 10,
+    },
+    Difficulty {
+        name: "Intermediate",
+        width: 16,
+        height: 16,
+        mines: 40,
+    },
+    Difficulty {
+        name: "Expert",
+        width: 30,
+        height: 16,
+        mines: 99,
+    },
+];
+
+impl Difficulty {
+    /// Looks up a difficulty by its name, in any case, as given on the command line
+    pub fn from_name(name: &str) -> Option<&'static Difficulty> {
+        DIFFICULTIES
+            .iter()
+            .find(|difficulty| difficulty.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether a flat board of this size with this many mines is this difficulty's
+    pub fn is_board(&self, width: usize, height: usize, mines: usize) -> bool {
+        (self.width, self.height, self.mines) == (width, height, mines)
+    }
+
+    /// `setup` with this difficulty's board, which is flat, has one mine a cell and only counts
+    /// the cells touching each one, with no number cap. The other custom choices still apply.
+    pub fn setup(&'static self, setup: &Setup) -> Setup {
+        let setup = Setup {
+            width: self.width,
+            height: self.height,
+            mines: self.mines,
+            depth: 1,
+            per_cell: 1,
+            radius: 1,
+            preset: Some(self.name),
+            ..*setup
+        };
+        Setup {
+            number_cap: setup.highest(),
+            ..setup
+        }
+    }
+}
+
+/// Everything chosen on the title screen that a new game is made from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setup {
+    pub width: usize,
+    pub height: usize,
+    /// How many layers are stacked up, which is 1 for a flat board
+    pub depth: usize,
+    pub mines: usize,
+    /// What percentage of the cells with mines hold anti-mines
+    pub anti_mines: usize,
+    pub per_cell: u8,
+    pub lives: usize,
+    pub opening: Opening,
+    /// The shape of the grid
+    pub topology: Topology,
+    /// How far away neighbours can be, which stays at 1 on grids without a radius
+    pub radius: usize,
+    /// The highest number a cell may show. Anything from the highest possible number up means
+    /// there is no cap.
+    pub number_cap: i16,
+    /// The name of the difficulty the board was picked from, unless it was made on the custom
+    /// form
+    pub preset: Option<&'static str>,
+    /// Where the mines go, when the board should come out the same every time
+    pub seed: Option<u64>,
+    pub rules: Rules,
+}
+
+impl Default for Setup {
+    fn default() -> Setup {
+        let beginner = &DIFFICULTIES[0];
+        Setup {
+            width: beginner.width,
+            height: beginner.height,
+            depth: 1,
+            mines: beginner.mines,
+            anti_mines: 0,
+            per_cell: 1,
+            lives: 1,
+            opening: Opening::default(),
+            topology: Topology::default(),
+            radius: 1,
+            number_cap: 8,
+            preset: Some(beginner.name),
+            seed: None,
+            rules: Rules::default(),
+        }
+    }
+}
+
+/// The most lives that can be chosen
+const MAX_LIVES: usize = 99;
+
+/// The furthest away neighbours can be
+pub const MAX_RADIUS: usize = 3;
+
+/// The longest time limit that can be chosen, in seconds
+pub const MAX_TIME_LIMIT: u64 = 24 * 60 * 60;
+
+impl Setup {
+    /// The board this setup makes, or why it can't be made
+    pub fn board(&self) -> Result<Board, BoardError> {
+        let mut board = Board::new_layered(
+            self.width,
+            self.height,
+            self.depth,
+            self.mines,
+            self.per_cell,
+        )?;
+        board.number_cap = self.cap();
+        board.anti_mines = self.anti_mines;
+        board.fog = self.rules.fog;
+        board.topology = self.topology;
+        if self.topology.has_radius() {
+            board.radius = self.radius;
+        }
+        if let Some(seed) = self.seed {
+            board.seed(seed);
+        }
+        Ok(board)
+    }
+
+    /// The highest number a cell can show on this board without a cap
+    pub fn highest(&self) -> i16 {
+        let most = self.topology.most_neighbours(self.radius, self.depth);
+        (most * self.per_cell as usize) as i16
+    }
+
+    /// How many cells the board has, on every layer
+    pub fn cells(&self) -> usize {
+        self.width * self.height * self.depth
+    }
+
+    /// The number cap, unless it is too high to ever matter
+    pub fn cap(&self) -> Option<i16> {
+        (self.number_cap < self.highest()).then_some(self.number_cap)
+    }
+
+    /// Changes which cells are neighbours with `change`. A board without a number cap stays
+    /// without one, rather than getting one just because its numbers can go higher.
+    pub fn reshape(&mut self, change: impl FnOnce(&mut Setup)) {
+        let uncapped = self.cap().is_none();
+        change(self);
+        if uncapped {
+            self.number_cap = self.highest();
+        }
+    }
+
+    /// Keeps every field within what makes sense for the others, so that the form is always
+    /// showing a board that can be played
+    pub fn clamp(&mut self) {
+        self.width = self.width.clamp(1, MAX_SIZE);
+        self.height = self.height.clamp(1, MAX_SIZE);
+        self.depth = self.depth.clamp(1, MAX_LAYERS);
+        self.per_cell = self.per_cell.clamp(1, MAX_MINES_PER_CELL);
+        let cells = self.cells();
+        // At least one cell has to be left without a mine
+        let most = (cells.saturating_sub(1) * self.per_cell as usize).max(1);
+        self.mines = self.mines.clamp(1, most);
+        self.anti_mines = self.anti_mines.min(100);
+        self.lives = self.lives.clamp(1, MAX_LIVES);
+        self.radius = match self.topology.has_radius() {
+            true => self.radius.clamp(1, MAX_RADIUS),
+            false => 1,
+        };
+        self.opening.min_size = self.opening.min_size.min(cells);
+        self.number_cap = self.number_cap.clamp(0, self.highest());
+        let rules = &mut self.rules;
+        rules.time_limit = rules.time_limit.map(|limit| limit.min(MAX_TIME_LIMIT));
+    }
+}
+
+/// The fields of the custom game form, in the order they are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Width,
+    Height,
+    Layers,
+    Mines,
+    AntiMines,
+    PerCell,
+    Topology,
+    Radius,
+    Lives,
+    SafeOpening,
+    MinOpening,
+    NoGuess,
+    NoFlags,
+    TimeLimit,
+    Zen,
+    Fog,
+    MovingMines,
+    NumberCap,
+    Seed,
+}
+
+const FIELDS: [Field; 19] = [
+    Field::Width,
+    Field::Height,
+    Field::Layers,
+    Field::Mines,
+    Field::AntiMines,
+    Field::PerCell,
+    Field::Topology,
+    Field::Radius,
+    Field::Lives,
+    Field::SafeOpening,
+    Field::MinOpening,
+    Field::NoGuess,
+    Field::NoFlags,
+    Field::TimeLimit,
+    Field::Zen,
+    Field::Fog,
+    Field::MovingMines,
+    Field::NumberCap,
+    Field::Seed,
+];
+
+impl Field {
+    fn label(self) -> &'static str {
+        match self {
+            Field::Width => "Width",
+            Field::Height => "Height",
+            Field::Layers => "Layers",
+            Field::Mines => "Mines",
+            Field::AntiMines => "Anti-mines",
+            Field::PerCell => "Most mines in a cell",
+            Field::Topology => "Grid",
+            Field::Radius => "Neighbour radius",
+            Field::Lives => "Lives",
+            Field::SafeOpening => "Empty first reveal",
+            Field::MinOpening => "Fewest cells first opened",
+            Field::NoGuess => "No guessing",
+            Field::NoFlags => "No flags",
+            Field::TimeLimit => "Time limit in seconds",
+            Field::Zen => "Zen mode",
+            Field::Fog => "Fog of war radius",
+            Field::MovingMines => "Reveals between mines moving",
+            Field::NumberCap => "Highest number",
+            Field::Seed => "Seed",
+        }
+    }
+}
+
+/// Which part of the title screen is showing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    /// The list of difficulties, with custom after them, along with the selected one
+    Difficulties(usize),
+    /// The custom game form, along with the selected field
+    Custom(usize),
+}
+
+/// What a key press on the title screen leads to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleOutcome {
+    /// Stay on the title screen
+    Continue,
+    Start(Setup),
+    /// Leave without starting a game
+    Cancel,
+    Quit,
+}
+
+/// The screen shown before a game, where the board is chosen
+pub struct TitleScreen {
+    stage: Stage,
+    setup: Setup,
+    /// Problems worth telling the player about before they start, shown under everything else
+    pub notices: Vec<String>,
+    /// Why the last key pressed on the custom form couldn't be used, until the next one
+    rejected: Option<String>,
+    /// The digits typed into the mines field, as they were typed rather than brought within the
+    /// board, so that a percentage is worked out from what the player meant
+    typed_mines: Option<String>,
+}
+
+impl TitleScreen {
+    /// Starts on the list of difficulties with the one `setup` came from selected, or custom if
+    /// it didn't come from one, with the custom form filled in from `setup`
+    pub fn new(setup: Setup) -> TitleScreen {
+        let selected = DIFFICULTIES
+            .iter()
+            .position(|difficulty| Some(difficulty.name) == setup.preset)
+            .unwrap_or(DIFFICULTIES.len());
+        TitleScreen {
+            stage: Stage::Difficulties(selected),
+            setup,
+            notices: Vec::new(),
+            rejected: None,
+            typed_mines: None,
+        }
+    }
+
+    pub fn handle(&mut self, key: Key, keymap: &Keymap) -> TitleOutcome {
+        match self.stage {
+            Stage::Difficulties(selected) => {
+                if keymap.action(key) == Some(Action::Quit) {
+                    return TitleOutcome::Quit;
+                }
+                let count = DIFFICULTIES.len() + 1;
+                let picked = match key {
+                    Key::Char(digit) => digit.to_digit(10).map(|digit| digit as usize),
+                    _ => None,
+                };
+                let (selected, input) = match picked {
+                    // A difficulty's number picks it straight away
+                    Some(number) if (1..=count).contains(&number) => {
+                        (number - 1, MenuInput::Choose)
+                    }
+                    _ => (selected, menu_input(key, keymap, selected, count)),
+                };
+                match input {
+                    MenuInput::Select(selected) => self.stage = Stage::Difficulties(selected),
+                    MenuInput::Choose if selected == DIFFICULTIES.len() => {
+                        self.stage = Stage::Custom(0)
+                    }
+                    MenuInput::Choose => {
+                        return TitleOutcome::Start(DIFFICULTIES[selected].setup(&self.setup))
+                    }
+                    MenuInput::Close => return TitleOutcome::Cancel,
+                    MenuInput::None => {}
+                }
+            }
+            Stage::Custom(selected) => {
+                let field = FIELDS[selected];
+                self.rejected = None;
+                let typed = self.typed_mines.take();
+                let typed = || typed.unwrap_or_else(|| self.setup.mines.to_string());
+                match key {
+                    Key::Char(digit @ '0'..='9') if field == Field::Mines => {
+                        let mut typed = typed();
+                        typed.push(digit);
+                        self.setup.mines = typed_number(&typed);
+                        self.typed_mines = Some(typed);
+                    }
+                    Key::Backspace if field == Field::Mines => {
+                        let mut typed = typed();
+                        typed.pop();
+                        self.setup.mines = typed_number(&typed);
+                        self.typed_mines = Some(typed);
+                    }
+                    Key::Char(digit @ '0'..='9') => {
+                        let digit = digit.to_digit(10).unwrap_or(0) as usize;
+                        self.edit(field, |value| {
+                            value.saturating_mul(10).saturating_add(digit)
+                        });
+                    }
+                    Key::Backspace => self.edit(field, |value| value / 10),
+                    Key::Char('%') if field == Field::Mines => {
+                        // What was typed is taken as a percentage of the cells
+                        let cells = self.setup.cells();
+                        match parse_mine_count(&format!("{}%", typed()), cells) {
+                            Ok(mines) => self.setup.mines = mines,
+                            Err(error) => self.rejected = Some(error),
+                        }
+                    }
+                    _ => match keymap.action(key) {
+                        Some(Action::Move(Direction::Left)) => {
+                            self.edit(field, |value| value.saturating_sub(1))
+                        }
+                        Some(Action::Move(Direction::Right)) => self.edit(field, |value| value + 1),
+                        _ => match menu_input(key, keymap, selected, FIELDS.len()) {
+                            MenuInput::Select(selected) => self.stage = Stage::Custom(selected),
+                            MenuInput::Choose if self.setup.board().is_ok() => {
+                                let setup = Setup {
+                                    preset: None,
+                                    ..self.setup
+                                };
+                                return TitleOutcome::Start(setup);
+                            }
+                            MenuInput::Close => {
+                                self.stage = Stage::Difficulties(DIFFICULTIES.len())
+                            }
+                            MenuInput::Choose | MenuInput::None => {}
+                        },
+                    },
+                }
+                self.setup.clamp();
+            }
+        }
+        TitleOutcome::Continue
+    }
+
+    /// Changes the value of a field. Switches flip whatever the change is.
+    fn edit(&mut self, field: Field, change: impl Fn(usize) -> usize) {
+        let setup = &mut self.setup;
+        match field {
+            Field::Width => setup.width = change(setup.width),
+            Field::Height => setup.height = change(setup.height),
+            Field::Mines => setup.mines = change(setup.mines),
+            Field::AntiMines => setup.anti_mines = change(setup.anti_mines),
+            Field::PerCell => {
+                let per_cell = change(setup.per_cell as usize);
+                setup.per_cell = per_cell.min(MAX_MINES_PER_CELL as usize) as u8;
+            }
+            Field::Layers => setup.reshape(|setup| {
+                setup.depth = change(setup.depth).min(MAX_LAYERS);
+            }),
+            Field::Topology => setup.reshape(|setup| setup.topology = setup.topology.next()),
+            Field::Radius => setup.reshape(|setup| {
+                setup.radius = change(setup.radius).min(MAX_RADIUS);
+            }),
+            Field::Lives => setup.lives = change(setup.lives),
+            Field::SafeOpening => setup.opening.clear_neighbours = !setup.opening.clear_neighbours,
+            Field::MinOpening => setup.opening.min_size = change(setup.opening.min_size),
+            Field::NoGuess => setup.opening.no_guess = !setup.opening.no_guess,
+            Field::NoFlags => setup.rules.no_flags = !setup.rules.no_flags,
+            // Like the seed, 0 means there isn't one. Zen mode doesn't have one either.
+            Field::TimeLimit => {
+                let limit = change(setup.rules.time_limit.unwrap_or(0) as usize) as u64;
+                setup.rules = setup.rules.unzen();
+                setup.rules.time_limit = (limit > 0).then_some(limit);
+            }
+            Field::Zen => {
+                setup.rules = match setup.rules.is_zen() {
+                    true => setup.rules.unzen(),
+                    false => setup.rules.zen(),
+                }
+            }
+            // Like the seed, 0 means there isn't any
+            Field::Fog => {
+                let fog = change(setup.rules.fog.unwrap_or(0)).min(MAX_SIZE);
+                setup.rules.fog = (fog > 0).then_some(fog);
+            }
+            Field::MovingMines => {
+                let every = change(setup.rules.moving_mines.unwrap_or(0));
+                setup.rules.moving_mines = (every > 0).then_some(every);
+            }
+            Field::NumberCap => {
+                let cap = change(setup.number_cap as usize);
+                setup.number_cap = cap.min(i16::MAX as usize) as i16;
+            }
+            // Taking the seed down to 0 goes back to a random one
+            Field::Seed => {
+                let seed = change(setup.seed.unwrap_or(0) as usize) as u64;
+                setup.seed = (seed > 0).then_some(seed);
+            }
+        }
+    }
+
+    /// The lines of the title screen as it currently stands
+    pub fn lines(&self, keymap: &Keymap) -> Vec<String> {
+        let mut lines = vec!["Minesweeper".to_string(), String::new()];
+        match self.stage {
+            Stage::Difficulties(selected) => {
+                let mut labels: Vec<String> = DIFFICULTIES
+                    .iter()
+                    .map(|difficulty| {
+                        format!(
+                            "{:<12} {:>2} x {:<2} {:>3} mines",
+                            difficulty.name, difficulty.width, difficulty.height, difficulty.mines
+                        )
+                    })
+                    .collect();
+                labels.push("Custom".to_string());
+                lines.extend(menu_lines(&labels, selected));
+                lines.push(String::new());
+                lines.push(format!(
+                    "enter or 1-{}: choose, {}: quit",
+                    DIFFICULTIES.len() + 1,
+                    keymap.describe(Action::Quit)
+                ));
+            }
+            Stage::Custom(selected) => {
+                let labels: Vec<String> = FIELDS
+                    .iter()
+                    .map(|&field| format!("{}: {}", field.label(), self.value(field)))
+                    .collect();
+                lines.extend(menu_lines(&labels, selected));
+                lines.push(String::new());
+                // The form only ever holds boards that can be made, but say why if not
+                lines.push(match (&self.rejected, self.setup.board()) {
+                    (Some(rejected), _) => rejected.clone(),
+                    (None, Ok(_)) => String::new(),
+                    (None, Err(error)) => error.to_string(),
+                });
+                lines.push(
+                    "\u{2190}\u{2192} or digits: change, %: mines as a percentage".to_string(),
+                );
+                lines.push("enter: start, esc: back".to_string());
+            }
+        }
+        for notice in &self.notices {
+            lines.push(String::new());
+            lines.push(notice.clone());
+        }
+        lines
+    }
+
+    fn value(&self, field: Field) -> String {
+        let setup = &self.setup;
+        let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+        match field {
+            Field::Width => setup.width.to_string(),
+            Field::Height => setup.height.to_string(),
+            Field::Layers => setup.depth.to_string(),
+            Field::Mines => {
+                let cells = setup.cells();
+                let percentage = setup.mines * 100 / cells;
+                format!("{} ({}%)", setup.mines, percentage)
+            }
+            Field::AntiMines => format!("{}% of the mines", setup.anti_mines),
+            Field::PerCell => setup.per_cell.to_string(),
+            Field::Topology => setup.topology.name().to_string(),
+            Field::Radius if !setup.topology.has_radius() => "1 on this grid".to_string(),
+            Field::Radius => setup.radius.to_string(),
+            Field::Lives => setup.lives.to_string(),
+            Field::SafeOpening => on_off(setup.opening.clear_neighbours),
+            Field::MinOpening => setup.opening.min_size.to_string(),
+            Field::NoGuess => on_off(setup.opening.no_guess),
+            Field::NoFlags => on_off(setup.rules.no_flags),
+            Field::TimeLimit => match setup.rules.time_limit {
+                Some(limit) => {
+                    let time = format_duration(std::time::Duration::from_secs(limit));
+                    format!("{} ({})", limit, time)
+                }
+                None => "none".to_string(),
+            },
+            Field::Zen => on_off(setup.rules.is_zen()),
+            Field::Fog => match setup.rules.fog {
+                Some(fog) => format!("{} cells", fog),
+                None => "off".to_string(),
+            },
+            Field::MovingMines => match setup.rules.moving_mines {
+                Some(every) => every.to_string(),
+                None => "never".to_string(),
+            },
+            Field::NumberCap => match setup.cap() {
+                Some(cap) => cap.to_string(),
+                None => "no cap".to_string(),
+            },
+            Field::Seed => match setup.seed {
+                Some(seed) => seed.to_string(),
+                None => "random".to_string(),
+            },
+        }
+    }
+}
+
+/// The number that digits typed into a field make, which stops at the largest there is
+fn typed_number(typed: &str) -> usize {
+    typed.chars().fold(0, |value: usize, digit| {
+        let digit = digit.to_digit(10).unwrap_or(0) as usize;
+        value.saturating_mul(10).saturating_add(digit)
+    })
+}
+
+/// Reads a mine count that is either an absolute number or a percentage of the board's cells,
+/// like `15%`. Percentages are rounded and kept between 1 and one less than the number of cells.
+pub(crate) fn parse_mine_count(input: &str, cells: usize) -> Result<usize, String> {
+    let percentage = match input.strip_suffix('%') {
+        Some(percentage) => percentage.trim(),
+        None => {
+            return input
+                .parse::<usize>()
+                .map_err(|_| "That isn't a valid number of mines".to_string())
+        }
+    };
+    let percentage = percentage
+        .parse::<f64>()
+        .map_err(|_| "That isn't a valid percentage".to_string())?;
+    if !(percentage > 0.0 && percentage < 100.0) {
+        return Err("The percentage must be between 0% and 100%".to_string());
+    }
+    let mines = (cells as f64 * percentage / 100.0).round() as usize;
+    Ok(mines.clamp(1, cells.saturating_sub(1).max(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mine_counts_can_be_numbers() {
+        assert_eq!(parse_mine_count("10", 81), Ok(10));
+        assert!(parse_mine_count("ten", 81).is_err());
+        assert!(parse_mine_count("", 81).is_err());
+    }
+
+    #[test]
+    fn percentages_are_rounded_to_the_nearest_mine() {
+        assert_eq!(parse_mine_count("15%", 81), Ok(12));
+        assert_eq!(parse_mine_count("50%", 81), Ok(41));
+        assert_eq!(parse_mine_count("12.5 %", 480), Ok(60));
+    }
+
+    #[test]
+    fn percentages_leave_a_mine_and_a_safe_cell() {
+        assert_eq!(parse_mine_count("1%", 10), Ok(1));
+        assert_eq!(parse_mine_count("99.9%", 10), Ok(9));
+    }
+
+    #[test]
+    fn percentages_have_to_be_between_0_and_100() {
+        assert!(parse_mine_count("0%", 81).is_err());
+        assert!(parse_mine_count("100%", 81).is_err());
+        assert!(parse_mine_count("-5%", 81).is_err());
+        assert!(parse_mine_count("lots%", 81).is_err());
+        assert!(parse_mine_count("%", 81).is_err());
+    }
+
+    /// The title screen on the custom form for `setup`, with the field `field` selected
+    fn form(setup: Setup, field: Field) -> TitleScreen {
+        let mut title = TitleScreen::new(Setup {
+            preset: None,
+            ..setup
+        });
+        title.stage = Stage::Custom(FIELDS.iter().position(|&f| f == field).unwrap());
+        title
+    }
+
+    /// Presses each key in turn, giving back what the last one did
+    fn press(title: &mut TitleScreen, keys: &[Key]) -> TitleOutcome {
+        let keymap = Keymap::default();
+        let mut outcome = TitleOutcome::Continue;
+        for &key in keys {
+            outcome = title.handle(key, &keymap);
+        }
+        outcome
+    }
+
+    #[test]
+    fn typed_percentages_are_of_what_was_typed() {
+        let small = Setup {
+            width: 4,
+            height: 4,
+            mines: 1,
+            ..Setup::default()
+        };
+        let mut title = form(small, Field::Mines);
+        press(
+            &mut title,
+            &[Key::Backspace, Key::Char('2'), Key::Char('0')],
+        );
+        // The form can only show as many mines as fit
+        assert_eq!(title.setup.mines, 15);
+        press(&mut title, &[Key::Char('%')]);
+        assert_eq!(title.setup.mines, 3);
+        assert_eq!(title.rejected, None);
+
+        press(
+            &mut title,
+            &[Key::Char('0'), Key::Char('0'), Key::Char('%')],
+        );
+        assert_eq!(title.setup.mines, 15);
+        assert!(title.rejected.is_some());
+        press(&mut title, &[Key::Down]);
+        assert_eq!(title.rejected, None);
+    }
+
+    #[test]
+    fn the_form_moves_between_fields_and_changes_them() {
+        let mut title = TitleScreen::new(Setup::default());
+        // Custom comes after the difficulties, so going up from the first reaches it
+        press(&mut title, &[Key::Up, Key::Char('\n')]);
+        assert_eq!(title.stage, Stage::Custom(0));
+        press(&mut title, &[Key::Down, Key::Char('j')]);
+        assert_eq!(title.stage, Stage::Custom(2));
+        press(&mut title, &[Key::Up]);
+        assert_eq!(title.stage, Stage::Custom(1));
+        press(&mut title, &[Key::Right, Key::Right, Key::Left]);
+        assert_eq!(title.setup.height, 10);
+        press(&mut title, &[Key::Backspace]);
+        assert_eq!(title.setup.height, 1);
+        press(&mut title, &[Key::Char('6')]);
+        assert_eq!(title.setup.height, 16);
+        // Going up from the first field wraps round to the last
+        press(&mut title, &[Key::Up, Key::Up]);
+        assert_eq!(title.stage, Stage::Custom(FIELDS.len() - 1));
+    }
+
+    #[test]
+    fn the_form_keeps_a_safe_cell() {
+        let small = Setup {
+            width: 3,
+            height: 3,
+            ..Setup::default()
+        };
+        let mut title = form(small, Field::Mines);
+        press(&mut title, &[Key::Char('9'), Key::Char('9')]);
+        assert_eq!(title.setup.mines, 8);
+        let mut title = form(small, Field::Width);
+        press(&mut title, &[Key::Backspace, Key::Backspace, Key::Left]);
+        assert_eq!(title.setup.width, 1);
+        assert_eq!(title.setup.mines, 2);
+    }
+
+    #[test]
+    fn starting_from_the_form_is_a_custom_board() {
+        let mut title = TitleScreen::new(Setup::default());
+        press(&mut title, &[Key::Up, Key::Char('\n'), Key::Right]);
+        let TitleOutcome::Start(setup) = press(&mut title, &[Key::Char('\n')]) else {
+            panic!("The form didn't start a game");
+        };
+        assert_eq!((setup.width, setup.height, setup.mines), (10, 9, 10));
+        assert_eq!(setup.preset, None);
+    }
+
+    #[test]
+    fn escape_goes_back_and_q_quits() {
+        let mut title = form(Setup::default(), Field::Mines);
+        assert_eq!(press(&mut title, &[Key::Esc]), TitleOutcome::Continue);
+        assert_eq!(title.stage, Stage::Difficulties(DIFFICULTIES.len()));
+        assert_eq!(press(&mut title, &[Key::Esc]), TitleOutcome::Cancel);
+        assert_eq!(press(&mut title, &[Key::Char('q')]), TitleOutcome::Quit);
+        // A difficulty's number starts it straight away
+        let TitleOutcome::Start(setup) = press(&mut title, &[Key::Char('2')]) else {
+            panic!("The number didn't start a game");
+        };
+        assert_eq!(setup.preset, Some(DIFFICULTIES[1].name));
+    }
+
+    #[test]
+    fn difficulties_count_only_the_cells_touching() {
+        let mut setup = Setup::default();
+        setup.reshape(|setup| setup.radius = 2);
+        setup.reshape(|setup| setup.depth = 3);
+        setup.per_cell = 3;
+        let expert = Difficulty::from_name("expert").unwrap().setup(&setup);
+        assert_eq!((expert.radius, expert.depth, expert.per_cell), (1, 1, 1));
+        assert_eq!(expert.cap(), None);
+        let hex = Setup {
+            topology: Topology::Hex,
+            ..setup
+        };
+        let expert = Difficulty::from_name("expert").unwrap().setup(&hex);
+        assert_eq!((expert.number_cap, expert.cap()), (6, None));
+    }
+
+    #[test]
+    fn reshaping_an_uncapped_board_keeps_it_uncapped() {
+        let mut setup = Setup::default();
+        setup.reshape(|setup| setup.radius = 2);
+        assert_eq!((setup.number_cap, setup.cap()), (24, None));
+        setup.number_cap = 10;
+        setup.reshape(|setup| setup.radius = 3);
+        assert_eq!(setup.cap(), Some(10));
+    }
+}
@@ -0,0 +1,196 @@
+/// The shape of the grid, which decides which cells are next to each other. Everything that
+/// looks at a cell's neighbours, from the numbers to flood fill and chording, goes through this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Square cells, each touching the eight around it
+    #[default]
+    Square,
+    /// Hexagonal cells in rows, each touching six others. Odd rows are pushed half a cell to the
+    /// right, so the rows above and below a cell meet it at different columns depending on
+    /// whether its row is odd or even.
+    Hex,
+    /// Square cells on a board whose left edge meets its right edge and top edge meets its
+    /// bottom, so that every cell has eight neighbours and openings flow across the seams
+    Torus,
+    /// Square cells whose numbers count the mines a chess knight's move away, rather than the
+    /// ones touching them
+    Knight,
+    /// Square cells touching only the four cells beside, above and below them, and not the ones
+    /// on the diagonals
+    VonNeumann,
+}
+
+/// Every cell up to `radius` rows and columns from a cell that `within` takes, as rows and
+/// columns from it, going along each row from the top
+fn around(radius: usize, within: impl Fn(isize, isize) -> bool) -> Vec<(isize, isize)> {
+    let radius = radius as isize;
+    let mut offsets = Vec::new();
+    for rows in -radius..=radius {
+        for cols in -radius..=radius {
+            if (rows, cols) != (0, 0) && within(rows, cols) {
+                offsets.push((rows, cols));
+            }
+        }
+    }
+    offsets
+}
+
+/// The six cells around a hexagonal cell in an even row, and in an odd one
+const HEX_EVEN: [(isize, isize); 6] = [(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)];
+const HEX_ODD: [(isize, isize); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)];
+
+/// The eight cells a knight's move from a cell
+const KNIGHT: [(isize, isize); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+impl Topology {
+    /// The name the topology goes by on the command line, in the config file and on the title
+    /// screen
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Square => "square",
+            Topology::Hex => "hex",
+            Topology::Torus => "torus",
+            Topology::Knight => "knight",
+            Topology::VonNeumann => "von-neumann",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Topology> {
+        [
+            Topology::Square,
+            Topology::Hex,
+            Topology::Torus,
+            Topology::Knight,
+            Topology::VonNeumann,
+        ]
+        .into_iter()
+        .find(|topology| topology.name() == name)
+    }
+
+    /// The topology after this one, coming back round to the first
+    pub fn next(self) -> Topology {
+        match self {
+            Topology::Square => Topology::Hex,
+            Topology::Hex => Topology::Torus,
+            Topology::Torus => Topology::Knight,
+            Topology::Knight => Topology::VonNeumann,
+            Topology::VonNeumann => Topology::Square,
+        }
+    }
+
+    /// Where the neighbours of a cell in `row` are, as rows and columns from it, counting cells
+    /// up to `radius` steps away where the grid has a radius. Some of them can be off the board.
+    pub fn offsets(self, row: usize, radius: usize) -> Vec<(isize, isize)> {
+        match self {
+            // Everything in the square around the cell
+            Topology::Square | Topology::Torus => around(radius, |_, _| true),
+            Topology::Hex if row.is_multiple_of(2) => HEX_EVEN.to_vec(),
+            Topology::Hex => HEX_ODD.to_vec(),
+            Topology::Knight => KNIGHT.to_vec(),
+            // Everything that many steps away without going diagonally
+            Topology::VonNeumann => around(radius, |rows, cols| {
+                rows.unsigned_abs() + cols.unsigned_abs() <= radius
+            }),
+        }
+    }
+
+    /// The most neighbours a cell can have with `radius` on a board `depth` layers deep, which is
+    /// the highest number it can show when cells hold one mine each. The layers either side of a
+    /// cell add the cells over its neighbours and the one over the cell itself.
+    pub fn most_neighbours(self, radius: usize, depth: usize) -> usize {
+        let flat = self.offsets(0, radius).len();
+        flat + (depth - 1).min(2) * (flat + 1)
+    }
+
+    /// Whether how far away neighbours can be is up to the player. Hexagons and knight's moves
+    /// always stay as they are.
+    pub fn has_radius(self) -> bool {
+        matches!(
+            self,
+            Topology::Square | Topology::Torus | Topology::VonNeumann
+        )
+    }
+
+    /// Whether neighbours off one edge are found on the opposite edge rather than missing
+    pub fn wraps(self) -> bool {
+        self == Topology::Torus
+    }
+
+    /// Whether odd rows are drawn a column further along than even ones, to show how the
+    /// hexagons fit together
+    pub fn is_staggered(self) -> bool {
+        self == Topology::Hex
+    }
+
+    /// Where moving one step from a cell in `row` goes, as rows and columns. Up and down keep to
+    /// the same column, which is always a neighbour on a hex grid too, and the diagonals go to
+    /// whichever neighbours lie that way.
+    pub fn step(self, row: usize, rows: isize, cols: isize) -> (isize, isize) {
+        match self {
+            // Going up or down from an even row, the neighbours are in this column and the one
+            // to its left, and from an odd one this column and the one to its right
+            Topology::Hex if rows != 0 && cols != 0 => {
+                let shift = match (row.is_multiple_of(2), cols < 0) {
+                    (true, true) => -1,
+                    (false, false) => 1,
+                    _ => 0,
+                };
+                (rows, shift)
+            }
+            _ => (rows, cols),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    /// A board 5 cells square with the given topology
+    fn board(topology: Topology) -> Board {
+        let mut board = Board::new(5, 5, 1).unwrap();
+        board.topology = topology;
+        board
+    }
+
+    /// The neighbours of the cell at `row` and `col`, sorted
+    fn neighbours(board: &Board, row: usize, col: usize) -> Vec<usize> {
+        let mut neighbours = board.neighbours(row * board.width + col);
+        neighbours.sort_unstable();
+        neighbours
+    }
+
+    #[test]
+    fn torus_corners_touch_the_opposite_corners() {
+        let board = board(Topology::Torus);
+        let corner = neighbours(&board, 0, 0);
+        // The rows and columns either side, with the last ones wrapping round
+        assert_eq!(corner, [1, 4, 5, 6, 9, 20, 21, 24]);
+        assert!(corner.contains(&24));
+        for (row, col) in [(0, 4), (4, 0), (4, 4)] {
+            let opposite = neighbours(&board, row, col);
+            assert_eq!(opposite.len(), 8);
+            assert!(opposite.contains(&0));
+        }
+    }
+
+    #[test]
+    fn von_neumann_cells_touch_the_cells_alongside() {
+        let board = board(Topology::VonNeumann);
+        assert_eq!(neighbours(&board, 0, 0), [1, 5]);
+        assert_eq!(neighbours(&board, 0, 2), [1, 3, 7]);
+        assert_eq!(neighbours(&board, 2, 0), [5, 11, 15]);
+        assert_eq!(neighbours(&board, 2, 2), [7, 11, 13, 17]);
+        assert_eq!(neighbours(&board, 4, 4).len(), 2);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::board::{Board, CellChange};
+use std::collections::VecDeque;
+
+/// The cells a move opened, shown a ring at a time spreading out from where the move was made,
+/// so that a big opening can be seen happening rather than appearing all at once. The board has
+/// already been changed; only the drawing is held back, by keeping the cells still to be shown
+/// in `Board::veiled`.
+pub struct Wave {
+    /// The rings still to be shown, nearest first
+    rings: VecDeque<Vec<usize>>,
+}
+
+impl Wave {
+    /// Starts showing the cells that `changes` opened, from the ring around `origin`. There is
+    /// nothing worth animating unless they make up more than one ring.
+    pub fn start(board: &mut Board, origin: usize, changes: &[CellChange]) -> Option<Wave> {
+        let from = &board.cells[origin];
+        let mut rings: Vec<Vec<usize>> = Vec::new();
+        for change in changes {
+            let index = change.index;
+            if change.was_revealed || !board.cells[index].is_revealed {
+                continue;
+            }
+            let cell = &board.cells[index];
+            let ring = (cell.row.abs_diff(from.row))
+                .max(cell.col.abs_diff(from.col))
+                .max(cell.layer.abs_diff(from.layer));
+            if rings.len() <= ring {
+                rings.resize(ring + 1, Vec::new());
+            }
+            rings[ring].push(index);
+        }
+        let mut rings: VecDeque<Vec<usize>> =
+            rings.into_iter().filter(|ring| !ring.is_empty()).collect();
+        if rings.len() < 2 {
+            return None;
+        }
+        // The first ring is shown straight away, along with the rest of the move
+        rings.pop_front();
+        board.veiled = rings.iter().flatten().copied().collect();
+        Some(Wave { rings })
+    }
+
+    /// Shows the next ring, returning `false` once every ring has been shown
+    pub fn step(&mut self, board: &mut Board) -> bool {
+        match self.rings.pop_front() {
+            Some(ring) => {
+                for index in ring {
+                    board.veiled.remove(&index);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Shows every ring that is left at once
+    pub fn finish(self, board: &mut Board) {
+        board.veiled.clear();
+    }
+}